@@ -20,7 +20,7 @@ fn main() -> io::Result<()> {
     let next = |x: usize| (x + 1) % COLORS_FG.len();
 
     let terminal_size = get_terminal_size()?;
-    let terminal_size_str = format!("{:?}", terminal_size);
+    let terminal_size_str = format!("{terminal_size:?}");
     print!("{}", set_window_title(terminal_size_str).unwrap());
 
     let mut counter = 0;
@@ -41,7 +41,13 @@ fn main() -> io::Result<()> {
         }
         // q to quit
         if input.is_ok()
-            && input.unwrap() == Event::Key(Key::Char('q'), ButtonType::Press, Modifiers::NONE)
+            && input.unwrap()
+                == Event::Key(
+                    Key::Char('q'),
+                    ButtonType::Press,
+                    Modifiers::NONE,
+                    Decoding::Exact,
+                )
         {
             break;
         }
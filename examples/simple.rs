@@ -21,7 +21,10 @@ fn main() -> io::Result<()> {
 
     let string = "Hello, World!";
 
-    let adjusted_middle = (middle.0 - ((string.len() / 2) as u16), middle.1);
+    let adjusted_middle = (
+        middle.0 - u16::try_from(display_width(string) / 2).unwrap_or(0),
+        middle.1,
+    );
 
     print!(
         "{COLOR_RED_BG}{}{string}",
@@ -0,0 +1,118 @@
+#!/usr/bin/env python3
+"""Generates src/grapheme_tables.rs from the Unicode database bundled with Python
+
+Python doesn't expose the Grapheme_Cluster_Break or Extended_Pictographic properties so they are
+derived from the general categories as described in UAX #29, with the lists that can't be derived
+copied from GraphemeBreakProperty.txt and emoji-data.txt. Hangul syllables, CR and LF are handled
+in code.
+
+Run from the repository root: python3 scripts/grapheme_tables.py
+"""
+
+import sys
+import unicodedata
+
+PREPEND = [
+    (0x0600, 0x0605), (0x06DD, 0x06DD), (0x070F, 0x070F), (0x0890, 0x0891),
+    (0x08E2, 0x08E2), (0x0D4E, 0x0D4E), (0x110BD, 0x110BD), (0x110CD, 0x110CD),
+    (0x111C2, 0x111C3), (0x1193F, 0x1193F), (0x11941, 0x11941), (0x11A3A, 0x11A3A),
+    (0x11A84, 0x11A89), (0x11D46, 0x11D46),
+]
+
+# Spacing marks that are Grapheme_Extend or aren't Mc
+EXTEND_MC = [
+    (0x09BE, 0x09BE), (0x09D7, 0x09D7), (0x0B3E, 0x0B3E), (0x0B57, 0x0B57),
+    (0x0BBE, 0x0BBE), (0x0BD7, 0x0BD7), (0x0CC2, 0x0CC2), (0x0CD5, 0x0CD6),
+    (0x0D3E, 0x0D3E), (0x0D57, 0x0D57), (0x0DCF, 0x0DCF), (0x0DDF, 0x0DDF),
+    (0x1B35, 0x1B35), (0x302E, 0x302F), (0x1133E, 0x1133E), (0x11357, 0x11357),
+    (0x114B0, 0x114B0), (0x114BD, 0x114BD), (0x115AF, 0x115AF), (0x11930, 0x11930),
+    (0x1D165, 0x1D165), (0x1D16E, 0x1D172),
+]
+EXTEND_OTHER = [(0x200C, 0x200C), (0xFF9E, 0xFF9F), (0x1F3FB, 0x1F3FF), (0xE0020, 0xE007F)]
+SPACING_MARK_OTHER = [(0x0E33, 0x0E33), (0x0EB3, 0x0EB3)]
+
+EXTENDED_PICTOGRAPHIC = [
+    (0x00A9, 0x00A9), (0x00AE, 0x00AE), (0x203C, 0x203C), (0x2049, 0x2049),
+    (0x2122, 0x2122), (0x2139, 0x2139), (0x2194, 0x2199), (0x21A9, 0x21AA),
+    (0x231A, 0x231B), (0x2328, 0x2328), (0x2388, 0x2388), (0x23CF, 0x23CF),
+    (0x23E9, 0x23F3), (0x23F8, 0x23FA), (0x24C2, 0x24C2), (0x25AA, 0x25AB),
+    (0x25B6, 0x25B6), (0x25C0, 0x25C0), (0x25FB, 0x25FE), (0x2600, 0x2605),
+    (0x2607, 0x2612), (0x2614, 0x2685), (0x2690, 0x2705), (0x2708, 0x2712),
+    (0x2714, 0x2714), (0x2716, 0x2716), (0x271D, 0x271D), (0x2721, 0x2721),
+    (0x2728, 0x2728), (0x2733, 0x2734), (0x2744, 0x2744), (0x2747, 0x2747),
+    (0x274C, 0x274C), (0x274E, 0x274E), (0x2753, 0x2755), (0x2757, 0x2757),
+    (0x2763, 0x2767), (0x2795, 0x2797), (0x27A1, 0x27A1), (0x27B0, 0x27B0),
+    (0x27BF, 0x27BF), (0x2934, 0x2935), (0x2B05, 0x2B07), (0x2B1B, 0x2B1C),
+    (0x2B50, 0x2B50), (0x2B55, 0x2B55), (0x3030, 0x3030), (0x303D, 0x303D),
+    (0x3297, 0x3297), (0x3299, 0x3299), (0x1F000, 0x1F0FF), (0x1F10D, 0x1F10F),
+    (0x1F12F, 0x1F12F), (0x1F16C, 0x1F171), (0x1F17E, 0x1F17F), (0x1F18E, 0x1F18E),
+    (0x1F191, 0x1F19A), (0x1F1AD, 0x1F1E5), (0x1F201, 0x1F20F), (0x1F21A, 0x1F21A),
+    (0x1F22F, 0x1F22F), (0x1F232, 0x1F23A), (0x1F23C, 0x1F23F), (0x1F249, 0x1F3FA),
+    (0x1F400, 0x1F53D), (0x1F546, 0x1F64F), (0x1F680, 0x1F6FF), (0x1F774, 0x1F77F),
+    (0x1F7D5, 0x1F7FF), (0x1F80C, 0x1F80F), (0x1F848, 0x1F84F), (0x1F85A, 0x1F85F),
+    (0x1F888, 0x1F88F), (0x1F8AE, 0x1F8FF), (0x1F90C, 0x1F93A), (0x1F93C, 0x1F945),
+    (0x1F947, 0x1FAFF), (0x1FC00, 0x1FFFD),
+]
+
+
+def in_ranges(table, cp):
+    return any(start <= cp <= end for start, end in table)
+
+
+def property_of(cp):
+    if cp in (0x0D, 0x0A) or 0xAC00 <= cp <= 0xD7A3:
+        return None
+    category = unicodedata.category(chr(cp))
+    if cp == 0x200D:
+        return "Zwj"
+    if 0x1F1E6 <= cp <= 0x1F1FF:
+        return "RegionalIndicator"
+    if in_ranges(PREPEND, cp):
+        return "Prepend"
+    if in_ranges(EXTEND_MC, cp) or in_ranges(EXTEND_OTHER, cp) or category in ("Mn", "Me"):
+        return "Extend"
+    if category in ("Cc", "Cf", "Zl", "Zp"):
+        return "Control"
+    if category == "Mc" or in_ranges(SPACING_MARK_OTHER, cp):
+        return "SpacingMark"
+    if 0x1100 <= cp <= 0x115F or 0xA960 <= cp <= 0xA97C:
+        return "L"
+    if 0x1160 <= cp <= 0x11A7 or 0xD7B0 <= cp <= 0xD7C6:
+        return "V"
+    if 0x11A8 <= cp <= 0x11FF or 0xD7CB <= cp <= 0xD7FB:
+        return "T"
+    if in_ranges(EXTENDED_PICTOGRAPHIC, cp):
+        return "ExtendedPictographic"
+    return None
+
+
+def main():
+    entries = []
+    for cp in range(0x110000):
+        prop = property_of(cp)
+        if prop is None:
+            continue
+        if entries and entries[-1][2] == prop and entries[-1][1] == cp - 1:
+            entries[-1][1] = cp
+        else:
+            entries.append([cp, cp, prop])
+
+    lines = [
+        f"// Generated by scripts/grapheme_tables.py from Unicode {unicodedata.unidata_version}, "
+        "do not edit",
+        "",
+        "#![allow(clippy::unreadable_literal)]",
+        "",
+        "use crate::grapheme::GraphemeBreak as G;",
+        "",
+        "/// The grapheme cluster break property of every character that isn't `Other`",
+        "pub(crate) const GRAPHEME_BREAKS: &[(u32, u32, G)] = &[",
+    ]
+    lines += [f"    (0x{start:04X}, 0x{end:04X}, G::{prop})," for start, end, prop in entries]
+    lines.append("];")
+    with open("src/grapheme_tables.rs", "w") as f:
+        f.write("\n".join(lines) + "\n")
+
+
+if __name__ == "__main__":
+    sys.exit(main())
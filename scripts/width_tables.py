@@ -0,0 +1,73 @@
+#!/usr/bin/env python3
+"""Generates src/width_tables.rs from the Unicode database bundled with Python
+
+Run from the repository root: python3 scripts/width_tables.py
+"""
+
+import sys
+import unicodedata
+
+
+def ranges(predicate):
+    """Collects the code points matching the predicate into inclusive ranges"""
+    result = []
+    for cp in range(0x110000):
+        if not predicate(cp):
+            continue
+        if result and result[-1][1] == cp - 1:
+            result[-1][1] = cp
+        else:
+            result.append([cp, cp])
+    return result
+
+
+def is_zero(cp):
+    c = chr(cp)
+    # Hangul medial vowels and final consonants join onto the previous syllable
+    if 0x1160 <= cp <= 0x11FF or 0xD7B0 <= cp <= 0xD7FF:
+        return True
+    # The soft hyphen is usually displayed
+    if cp == 0x00AD:
+        return False
+    return unicodedata.category(c) in ("Mn", "Me", "Cf")
+
+
+def is_wide(cp):
+    return unicodedata.east_asian_width(chr(cp)) in ("W", "F") and not is_zero(cp)
+
+
+def is_ambiguous(cp):
+    c = chr(cp)
+    return (
+        unicodedata.east_asian_width(c) == "A"
+        and unicodedata.category(c) not in ("Co", "Cn")
+        and not is_zero(cp)
+    )
+
+
+def table(name, doc, entries):
+    lines = [f"/// {doc}", f"pub(crate) const {name}: &[(u32, u32)] = &["]
+    lines += [f"    (0x{start:04X}, 0x{end:04X})," for start, end in entries]
+    lines.append("];")
+    return "\n".join(lines)
+
+
+def main():
+    tables = [
+        table("ZERO", "Combining marks and format characters, which take up no columns",
+              ranges(is_zero)),
+        table("WIDE", "East Asian wide and fullwidth characters, including emoji",
+              ranges(is_wide)),
+        table("AMBIGUOUS", "East Asian ambiguous characters, wide in CJK contexts",
+              ranges(is_ambiguous)),
+    ]
+    header = (
+        f"// Generated by scripts/width_tables.py from Unicode {unicodedata.unidata_version}, "
+        "do not edit\n\n#![allow(clippy::unreadable_literal)]\n"
+    )
+    with open("src/width_tables.rs", "w") as f:
+        f.write(header + "\n" + "\n\n".join(tables) + "\n")
+
+
+if __name__ == "__main__":
+    sys.exit(main())
@@ -3,6 +3,18 @@
 //! These should work on *most* terminals
 //!
 //! For these to always work on Windows you need to run the `enable_ansi` function inside this module
+//!
+//! Every escape is available as a string constant or helper and as a typed [`Command`], which can
+//! be written to any [`io::Write`](std::io::Write) without allocating with [`queue!`] and
+//! [`execute!`]
+//!
+//! Colors and styles written by commands are dropped when they are disabled by the
+//! [`ColorPolicy`], which by default honours `NO_COLOR`, `FORCE_COLOR` and whether stdout is a
+//! terminal, wrap other streams in a [`ColorWriter`] to resolve it for them instead, the string
+//! constants are always emitted unless written through one
+//!
+//! [`queue!`]: crate::queue
+//! [`execute!`]: crate::execute
 
 #[cfg(unix)]
 pub use crate::unix::enable_ansi;
@@ -10,50 +22,116 @@ pub use crate::unix::enable_ansi;
 #[cfg(windows)]
 pub use crate::windows::enable_ansi;
 
+pub use crate::ansi_text::{parse_ansi, strip_ansi};
+pub use crate::color::{
+    Color, ColorDepth, ParseColorError, SetBackground, SetForeground, SetUnderlineColor,
+    color_depth, detect_color_depth, query_truecolor, set_color_depth,
+};
+pub use crate::command::{
+    Attribute, ClearAllTabStops, ClearTabStop, Command, CursorShape, DeleteChars, DeleteLines,
+    DisableKittyKeyboard, DisableMouseInput, EnableKittyKeyboard, EnableMouseInput, EnterAltScreen,
+    Erase, EraseChars, ExitAltScreen, InsertChars, InsertLines, MoveCursorDown, MoveCursorLeft,
+    MoveCursorRight, MoveCursorToColumn, MoveCursorToNextTab, MoveCursorToPosition,
+    MoveCursorToPreviousTab, MoveCursorToRow, MoveCursorUp, RepeatChar, ResetAttribute,
+    ResetScrollRegion, ResetStyle, RestoreCursorPosition, SaveCursorPosition, ScrollDown, ScrollUp,
+    SetAttribute, SetScrollRegion, SetTabStop, SetWindowTitle,
+};
+pub use crate::cursor::{HideCursor, ResetCursorColor, SetCursorColor, ShowCursor};
+pub use crate::grapheme::{
+    DisableGraphemeClusters, EnableGraphemeClusters, Graphemes, grapheme_clusters, grapheme_width,
+    graphemes, set_grapheme_clusters,
+};
+pub use crate::hyperlink::{
+    EndHyperlink, Link, StartHyperlink, hyperlinks_enabled, set_hyperlinks,
+};
+pub use crate::notification::{
+    Notification, NotificationProtocol, detect_notification_protocol, notification_protocol,
+    set_notification_protocol,
+};
+pub use crate::palette::{
+    ResetDefaultBackground, ResetDefaultForeground, ResetPalette, ResetPaletteColor,
+    SetDefaultBackground, SetDefaultForeground, SetPaletteColor,
+};
+pub use crate::policy::{
+    ColorPolicy, ColorWriter, color_policy, colors_enabled, colors_enabled_for, set_color_policy,
+};
+pub use crate::progress::{ProgressState, SetProgress};
+pub use crate::shell::{
+    CommandFinished, CommandStart, PromptEnd, PromptStart, SetWorkingDirectory,
+};
+pub use crate::style::{Attributes, Style, StyleTransition};
+pub use crate::synchronized::{
+    BeginSynchronizedUpdate, EndSynchronizedUpdate, SynchronizedUpdate, detect_synchronized_output,
+    set_synchronized_output, synchronized_output,
+};
+pub use crate::text::{Line, Span, Text};
+pub use crate::underline::{
+    SetUnderlineStyle, UnderlineStyle, detect_styled_underlines, set_styled_underlines,
+    styled_underlines,
+};
+pub use crate::width::{ambiguous_wide, char_width, display_width, set_ambiguous_wide};
+pub use crate::window::{
+    DeiconifyWindow, IconifyWindow, MaximizeWindow, PopWindowTitle, PushWindowTitle, SetIconName,
+    UnmaximizeWindow, query_cell_size, query_text_area_pixels, query_text_area_size,
+};
+
 /// Sets the terminal to an arbitrary 12-bit/truecolor color in the foreground when printed
 #[must_use]
 pub fn rgb_color_code_fg(red: u8, green: u8, blue: u8) -> String {
-    format!("\x1b[38;2;{red};{green};{blue}m")
+    SetForeground(Color::Rgb(red, green, blue)).to_string()
 }
 
 /// Sets the terminal to an arbitrary 12-bit/truecolor color in the background when printed
 #[must_use]
 pub fn rgb_color_code_bg(red: u8, green: u8, blue: u8) -> String {
-    format!("\x1b[48;2;{red};{green};{blue}m")
+    SetBackground(Color::Rgb(red, green, blue)).to_string()
 }
 
 /// Sets the title of the window when printed
+///
+/// Returns `None` if the title contains control characters, which could end the escape sequence
+/// early, push the old title with [`WINDOW_TITLE_PUSH`] first to be able to restore it
 #[must_use]
 pub fn set_window_title<T: Into<String>>(title: T) -> Option<String> {
     let title = title.into();
-    if title.len() > 255 {
+    if title.chars().any(char::is_control) {
         return None;
     }
-    Some(format!("\x1b]0;{title}\x1b\x5c"))
+    Some(SetWindowTitle(title).to_string())
+}
+
+/// Sets the progress indicator on the terminal's tab to {percent} when printed
+///
+/// Print the result, [`tui_deinit`](crate::control::tui_deinit) and
+/// [`cli_deinit`](crate::control::cli_deinit) clear the indicator if it was set through here
+#[must_use]
+pub fn set_progress(state: ProgressState, percent: u8) -> String {
+    crate::progress::track_progress(state);
+    SetProgress(state, percent).to_string()
 }
 
 /// Moves the cursor up {num} characters when printed
 #[must_use]
 pub fn move_cursor_up(num: u16) -> String {
-    format!("\x1b[{num}A")
+    MoveCursorUp(num).to_string()
 }
 
 /// Moves the cursor down {num} characters when printed
 #[must_use]
 pub fn move_cursor_down(num: u16) -> String {
-    format!("\x1b[{num}B")
+    MoveCursorDown(num).to_string()
 }
 
 /// Moves the cursor right {num} characters when printed
 #[must_use]
 pub fn move_cursor_right(num: u16) -> String {
-    format!("\x1b[{num}C")
+    MoveCursorRight(num).to_string()
 }
 
 /// Moves the cursor left {num} characters when printed
 #[must_use]
 pub fn move_cursor_left(num: u16) -> String {
-    format!("\x1b[{num}A")
+    MoveCursorLeft(num).to_string()
 }
 
 /// Moves the cursor to {row} when printed
@@ -61,7 +139,7 @@ pub fn move_cursor_left(num: u16) -> String {
 /// Origin is 0, 0
 #[must_use]
 pub fn move_cursor_to_row(line: u16) -> String {
-    format!("\x1b[{}d", line.saturating_add(1))
+    MoveCursorToRow(line).to_string()
 }
 
 /// Moves the cursor to {column} when printed
@@ -69,7 +147,7 @@ pub fn move_cursor_to_row(line: u16) -> String {
 /// Origin is 0, 0
 #[must_use]
 pub fn move_cursor_to_column(column: u16) -> String {
-    format!("\x1b[{}G", column.saturating_add(1))
+    MoveCursorToColumn(column).to_string()
 }
 
 /// Moves the cursor to Position {x}, {y} when printed
@@ -77,11 +155,7 @@ pub fn move_cursor_to_column(column: u16) -> String {
 /// Origin is 0, 0
 #[must_use]
 pub fn move_cursor_to_position(column: u16, line: u16) -> String {
-    format!(
-        "\x1b[{};{}H",
-        line.saturating_add(1),
-        column.saturating_add(1)
-    )
+    MoveCursorToPosition(column, line).to_string()
 }
 
 /// Saves the current cursor position
@@ -89,6 +163,25 @@ pub const CURSOR_POSITION_SAVE: &str = "\x1b7";
 /// Restores the saved cursor position
 pub const CURSOR_POSITION_RESTORE: &str = "\x1b8";
 
+/// Shows the cursor
+pub const CURSOR_SHOW: &str = "\x1b[?25h";
+/// Hides the cursor
+pub const CURSOR_HIDE: &str = "\x1b[?25l";
+/// Resets the color of the cursor to the user-specified default
+pub const CURSOR_COLOR_RESET: &str = "\x1b]112\x1b\\";
+
+/// Saves the window title and icon name on the terminal's title stack
+pub const WINDOW_TITLE_PUSH: &str = "\x1b[22;0t";
+/// Restores the window title and icon name saved by [`WINDOW_TITLE_PUSH`]
+pub const WINDOW_TITLE_POP: &str = "\x1b[23;0t";
+
+/// Resets every palette entry to the user-specified default
+pub const PALETTE_RESET: &str = "\x1b]104\x1b\\";
+/// Resets the default foreground color to the user-specified default
+pub const DEFAULT_FOREGROUND_RESET: &str = "\x1b]110\x1b\\";
+/// Resets the default background color to the user-specified default
+pub const DEFAULT_BACKGROUND_RESET: &str = "\x1b]111\x1b\\";
+
 /// Enters the alternate screen
 ///
 /// The alternate screen is a blank screen that won't interrupt the main screen (e.g. vi)
@@ -126,6 +219,30 @@ pub const ERASE_CURSOR_TO_LINE_START: &str = "\x1b[1K";
 /// Erases from the cursor to the line end while leaving cursor in place
 pub const ERASE_CURSOR_TO_LINE_END: &str = "\x1b[0K";
 
+/// Lets the whole screen scroll again after [`SetScrollRegion`]
+pub const SCROLL_REGION_RESET: &str = "\x1b[r";
+/// Scrolls the screen up a line, adding a blank line at the bottom
+pub const SCROLL_UP: &str = "\x1b[S";
+/// Scrolls the screen down a line, adding a blank line at the top
+pub const SCROLL_DOWN: &str = "\x1b[T";
+/// Inserts a blank line at the cursor, pushing the lines below down
+pub const LINE_INSERT: &str = "\x1b[L";
+/// Deletes the line at the cursor, pulling the lines below up
+pub const LINE_DELETE: &str = "\x1b[M";
+/// Inserts a blank character at the cursor, pushing the rest of the line right
+pub const CHAR_INSERT: &str = "\x1b[@";
+/// Deletes the character at the cursor, pulling the rest of the line left
+pub const CHAR_DELETE: &str = "\x1b[P";
+/// Erases the character at the cursor while leaving cursor in place
+pub const CHAR_ERASE: &str = "\x1b[X";
+
+/// Sets a tab stop at the cursor's column
+pub const TAB_STOP_SET: &str = "\x1bH";
+/// Clears the tab stop at the cursor's column
+pub const TAB_STOP_CLEAR: &str = "\x1b[0g";
+/// Clears every tab stop
+pub const TAB_STOP_CLEAR_ALL: &str = "\x1b[3g";
+
 /// Makes characters sent to the screen bold
 pub const STYLE_BOLD: &str = "\x1b[1m";
 /// Makes characters sent to the screen dim
@@ -136,6 +253,26 @@ pub const STYLE_ITALIC: &str = "\x1b[3m";
 ///
 /// This is less commonly supported than other styles
 pub const STYLE_UNDERLINE: &str = "\x1b[4m";
+/// Makes characters sent to the screen have a double underline
+///
+/// This is an extension from kitty, [`SetUnderlineStyle`] falls back to a single underline on
+/// terminals without it
+pub const STYLE_UNDERLINE_DOUBLE: &str = "\x1b[4:2m";
+/// Makes characters sent to the screen have a curly underline
+///
+/// This is an extension from kitty, [`SetUnderlineStyle`] falls back to a single underline on
+/// terminals without it
+pub const STYLE_UNDERLINE_CURLY: &str = "\x1b[4:3m";
+/// Makes characters sent to the screen have a dotted underline
+///
+/// This is an extension from kitty, [`SetUnderlineStyle`] falls back to a single underline on
+/// terminals without it
+pub const STYLE_UNDERLINE_DOTTED: &str = "\x1b[4:4m";
+/// Makes characters sent to the screen have a dashed underline
+///
+/// This is an extension from kitty, [`SetUnderlineStyle`] falls back to a single underline on
+/// terminals without it
+pub const STYLE_UNDERLINE_DASHED: &str = "\x1b[4:5m";
 /// Makes characters sent to the screen blinking
 ///
 /// This is less commonly supported than other styles
@@ -157,16 +294,20 @@ pub const STYLE_STRIKETHROUGH: &str = "\x1b[9m";
 pub const STYLE_RESET: &str = "\x1b[0m";
 /// Resets bold
 ///
-/// Often bold & dim's implementations are overlapping and will likely unset both
-pub const STYLE_RESET_BOLD: &str = "\x1b[21m";
+/// There is no separate reset for bold, this also resets dim, use [`Style::transition`] to keep
+/// dim set
+pub const STYLE_RESET_BOLD: &str = "\x1b[22m";
 /// Resets dim
 ///
-/// Often bold & dim's implementations are overlapping and will likely unset both
+/// There is no separate reset for dim, this also resets bold, use [`Style::transition`] to keep
+/// bold set
 pub const STYLE_RESET_DIM: &str = "\x1b[22m";
 /// Reset italic
 pub const STYLE_RESET_ITALIC: &str = "\x1b[23m";
 /// Reset underline
 pub const STYLE_RESET_UNDERLINE: &str = "\x1b[24m";
+/// Reset underline color to the foreground color
+pub const STYLE_RESET_UNDERLINE_COLOR: &str = "\x1b[59m";
 /// Reset blinking
 pub const STYLE_RESET_BLINKING: &str = "\x1b[25m";
 /// Reset reverse
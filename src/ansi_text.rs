@@ -0,0 +1,323 @@
+use crate::color::{Color, ColorDepth};
+use crate::command::Attribute;
+use crate::style::Style;
+use crate::text::{Line, Span, Text};
+use crate::underline::UnderlineStyle;
+
+/// A piece of a string that may contain escape sequences
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Token<'a> {
+    Text(&'a str),
+    /// A control sequence, `ESC [ params final`
+    Csi {
+        params: &'a str,
+        final_byte: u8,
+    },
+    /// An operating system command, `ESC ] content ST`
+    Osc(&'a str),
+    /// Any other escape sequence or control character
+    Other,
+}
+
+/// Splits a string into text and escape sequences
+struct Tokens<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Token<'a>> {
+        let bytes = self.rest.as_bytes();
+        let (token, len) = match *bytes.first()? {
+            b'\x1b' => match bytes.get(1) {
+                Some(b'[') => {
+                    let params = bytes[2..]
+                        .iter()
+                        .take_while(|b| (0x20..0x40).contains(*b))
+                        .count();
+                    match bytes.get(2 + params) {
+                        Some(&final_byte @ 0x40..=0x7e) => (
+                            Token::Csi {
+                                params: &self.rest[2..2 + params],
+                                final_byte,
+                            },
+                            3 + params,
+                        ),
+                        _ => (Token::Other, 2 + params),
+                    }
+                }
+                Some(&kind @ (b']' | b'P' | b'X' | b'^' | b'_')) => {
+                    let content = &self.rest[2..];
+                    let (end, terminator) = match content.find(['\x07', '\x1b']) {
+                        Some(end) if content.as_bytes()[end] == b'\x07' => (end, 1),
+                        Some(end) => (end, 2),
+                        None => (content.len(), 0),
+                    };
+                    let token = if kind == b']' {
+                        Token::Osc(&content[..end])
+                    } else {
+                        Token::Other
+                    };
+                    (token, (2 + end + terminator).min(bytes.len()))
+                }
+                Some(byte) if !byte.is_ascii() => (Token::Other, 1),
+                Some(_) => {
+                    let intermediates = bytes[1..]
+                        .iter()
+                        .take_while(|b| (0x20..0x30).contains(*b))
+                        .count();
+                    (Token::Other, (2 + intermediates).min(bytes.len()))
+                }
+                None => (Token::Other, 1),
+            },
+            b'\n' | b'\t' => (Token::Text(&self.rest[..1]), 1),
+            byte if byte.is_ascii_control() => (Token::Other, 1),
+            _ => {
+                let len = self
+                    .rest
+                    .find(|c: char| c.is_ascii_control())
+                    .unwrap_or(self.rest.len());
+                (Token::Text(&self.rest[..len]), len)
+            }
+        };
+        // Escape sequences are ASCII so this never splits a character unless the string is cut
+        // off in the middle of one, in which case everything left is dropped
+        self.rest = self.rest.get(len..).unwrap_or("");
+        Some(token)
+    }
+}
+
+const fn tokens(s: &str) -> Tokens<'_> {
+    Tokens { rest: s }
+}
+
+/// Removes every escape sequence and control character except newlines and tabs from a string
+///
+/// ```
+/// use neutuino::ansi::strip_ansi;
+///
+/// assert_eq!(strip_ansi("\x1b[1;31merror\x1b[0m: oops"), "error: oops");
+/// ```
+#[must_use]
+pub fn strip_ansi(s: &str) -> String {
+    tokens(s)
+        .filter_map(|token| match token {
+            Token::Text(text) => Some(text),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Parses a string containing color and style (SGR) and hyperlink (OSC 8) sequences into styled
+/// text, any other escape sequences and control characters are dropped
+///
+/// Styles carry over from one line to the next like they do in the terminal
+///
+/// ```
+/// use neutuino::ansi::{Color, Span, Style, parse_ansi};
+///
+/// let text = parse_ansi("\x1b[32mok\x1b[0m done");
+/// assert_eq!(text.lines[0].spans[0], Span::styled("ok", Style::new().fg(Color::Green)));
+/// ```
+#[must_use]
+pub fn parse_ansi(s: &str) -> Text {
+    let mut text = Text::new();
+    let mut current = Line::new();
+    let mut style = Style::new();
+    let mut link: Option<String> = None;
+    for token in tokens(s) {
+        match token {
+            Token::Text("\n") => text.push(std::mem::take(&mut current)),
+            Token::Text(content) => match current.spans.last_mut() {
+                Some(span) if span.style == style && span.link == link => {
+                    span.content.push_str(content);
+                }
+                _ => current.push(Span {
+                    content: content.to_string(),
+                    style,
+                    link: link.clone(),
+                }),
+            },
+            Token::Csi {
+                params,
+                final_byte: b'm',
+            } => apply_sgr(&mut style, params),
+            Token::Osc(content) => {
+                if let Some(hyperlink) = content.strip_prefix("8;") {
+                    let uri = hyperlink.split_once(';').map_or("", |(_, uri)| uri);
+                    link = (!uri.is_empty()).then(|| uri.to_string());
+                }
+            }
+            Token::Csi { .. } | Token::Other => {}
+        }
+    }
+    text.push(current);
+    text
+}
+
+/// Applies the parameters of an SGR sequence to a style
+fn apply_sgr(style: &mut Style, params: &str) {
+    let mut params = params.split(';');
+    while let Some(param) = params.next() {
+        let mut sub_params = param.split(':');
+        // An empty code is a reset, one that isn't a number is skipped rather than taken as one
+        let code = match sub_params.next().unwrap_or("") {
+            "" => 0,
+            code => match code.parse::<u8>() {
+                Ok(code) => code,
+                Err(_) => continue,
+            },
+        };
+        let attributes = &mut style.attributes;
+        match code {
+            0 => *style = Style::new(),
+            4 => {
+                let underline = match sub_params.next() {
+                    Some("0") => None,
+                    Some("2") => Some(UnderlineStyle::Double),
+                    Some("3") => Some(UnderlineStyle::Curly),
+                    Some("4") => Some(UnderlineStyle::Dotted),
+                    Some("5") => Some(UnderlineStyle::Dashed),
+                    _ => Some(UnderlineStyle::Single),
+                };
+                match underline {
+                    Some(underline) => *style = style.underline(underline),
+                    None => remove_underline(style),
+                }
+            }
+            21 => *style = style.underline(UnderlineStyle::Double),
+            1..=9 => {
+                if let Some(attribute) = attribute(code) {
+                    attributes.insert(attribute);
+                }
+            }
+            22 => {
+                attributes.remove(Attribute::Bold);
+                attributes.remove(Attribute::Dim);
+            }
+            24 => remove_underline(style),
+            // Proportional spacing, which nothing supports, not a blink reset
+            26 => {}
+            23..=29 => {
+                if let Some(attribute) = attribute(code - 20) {
+                    attributes.remove(attribute);
+                }
+            }
+            30..=37 => style.fg = palette(code - 30),
+            40..=47 => style.bg = palette(code - 40),
+            90..=97 => style.fg = palette(code - 90 + 8),
+            100..=107 => style.bg = palette(code - 100 + 8),
+            39 => style.fg = Color::Default,
+            49 => style.bg = Color::Default,
+            59 => style.underline_color = Color::Default,
+            38 | 48 | 58 => {
+                // Colors either use colons, `38:2::r:g:b` or `38:2:r:g:b`, or semicolons,
+                // `38;2;r;g;b`
+                let mut sub_params = sub_params.peekable();
+                let color = if sub_params.peek().is_some() {
+                    extended_color(&mut sub_params, true)
+                } else {
+                    extended_color(&mut params, false)
+                };
+                if let Some(color) = color {
+                    match code {
+                        38 => style.fg = color,
+                        48 => style.bg = color,
+                        _ => style.underline_color = color,
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Removes the underline, resetting its style so the style compares equal to one that never had it
+fn remove_underline(style: &mut Style) {
+    style.attributes.remove(Attribute::Underline);
+    style.underline_style = UnderlineStyle::Single;
+}
+
+/// The attribute set by an SGR code
+const fn attribute(code: u8) -> Option<Attribute> {
+    Some(match code {
+        1 => Attribute::Bold,
+        2 => Attribute::Dim,
+        3 => Attribute::Italic,
+        4 => Attribute::Underline,
+        5 | 6 => Attribute::Blinking,
+        7 => Attribute::Reverse,
+        8 => Attribute::Hidden,
+        9 => Attribute::Strikethrough,
+        _ => return None,
+    })
+}
+
+/// Parses the color after 38, 48 or 58, with colons rgb may start with a color space id
+fn extended_color<'a>(params: &mut impl Iterator<Item = &'a str>, colons: bool) -> Option<Color> {
+    let mut next = || params.next().map(|param| param.parse::<u8>().ok());
+    match next()?? {
+        5 => Some(palette(next()??)),
+        2 => {
+            let mut channels = [next()?, next()?, next()?];
+            if colons && let Some(blue) = next() {
+                channels = [channels[1], channels[2], blue];
+            }
+            Some(Color::Rgb(channels[0]?, channels[1]?, channels[2]?))
+        }
+        _ => None,
+    }
+}
+
+/// The color of a palette entry, using the named colors for the first 16
+fn palette(index: u8) -> Color {
+    if index < 16 {
+        Color::Indexed(index).downsample(ColorDepth::Ansi16)
+    } else {
+        Color::Indexed(index)
+    }
+}
+
+#[test]
+fn test_parse_ansi() {
+    let bold = Style::new().attribute(Attribute::Bold);
+    let text = parse_ansi(
+        "\x1b[1mwarning\x1b[22;38;2;255;128;0m: \x1b]8;;https://a.b\x07x\x1b]8;;\x1b\\\n\x1b[4:3;58:2::1:2:3mline\x1b[2K\r",
+    );
+    let orange = Style::new().fg(Color::Rgb(255, 128, 0));
+    let curly = orange
+        .underline(UnderlineStyle::Curly)
+        .underline_color(Color::Rgb(1, 2, 3));
+    assert_eq!(
+        text.lines,
+        [
+            Line::from(vec![
+                Span::styled("warning", bold),
+                Span::styled(": ", orange),
+                Span::styled("x", orange).link("https://a.b"),
+            ]),
+            Line::from(Span::styled("line", curly)),
+        ]
+    );
+    assert_eq!(
+        parse_ansi("\x1b[91;48;5;208mx").lines[0].spans[0].style,
+        Style::new().fg(Color::BrightRed).bg(Color::Indexed(208))
+    );
+    assert_eq!(
+        strip_ansi("a\x1b[31mb\x1b]0;title\x07c\x1bPq\x1b\\d\x1b7e\r\n\x1b["),
+        "abcde\n"
+    );
+    assert_eq!(strip_ansi("\x1bé"), "é");
+
+    let style = |sgr: &str| parse_ansi(&format!("{sgr}x")).lines[0].spans[0].style;
+    assert_eq!(style("\x1b[1;300m"), bold);
+    assert_eq!(
+        style("\x1b[1;;3m"),
+        Style::new().attribute(Attribute::Italic)
+    );
+    assert_eq!(style("\x1b[4:3;24m"), Style::new());
+    assert_eq!(style("\x1b[4:3m\x1b[4:0m"), Style::new());
+    let blinking = Style::new().attribute(Attribute::Blinking);
+    assert_eq!(style("\x1b[5;26m"), blinking);
+}
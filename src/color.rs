@@ -0,0 +1,491 @@
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::time::Duration;
+
+use crate::command::{Command, impl_display};
+use crate::policy::colors_enabled;
+use crate::query::{query, string_reply};
+use crate::underline::styled_underlines;
+
+/// A color that the terminal can display
+///
+/// Can be parsed from `#rrggbb`, `#rgb`, `rgb(r, g, b)`, a palette index (`0`-`255`) or a name
+/// such as `red`, `bright-red` or `default`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum Color {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+    BrightBlack,
+    BrightRed,
+    BrightGreen,
+    BrightYellow,
+    BrightBlue,
+    BrightMagenta,
+    BrightCyan,
+    BrightWhite,
+    /// The terminal's own foreground or background color
+    #[default]
+    Default,
+    /// An entry of the terminal's 256 color palette
+    ///
+    /// 0-15 are the named colors, 16-231 are a 6x6x6 color cube and 232-255 are a grayscale ramp
+    Indexed(u8),
+    /// An arbitrary 24-bit/truecolor color
+    Rgb(u8, u8, u8),
+}
+
+/// How many colors the terminal can display
+///
+/// Colors are downsampled to the nearest one the terminal supports whenever they are emitted by
+/// a command, see [`set_color_depth`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ColorDepth {
+    /// The 8 named colors and their bright variants, e.g. the Linux console
+    Ansi16,
+    /// The 256 color palette
+    Ansi256,
+    /// Arbitrary 24-bit colors
+    TrueColor,
+}
+
+/// 0 until the color depth has been detected or set
+static COLOR_DEPTH: AtomicU8 = AtomicU8::new(0);
+
+/// Sets the color depth colors are downsampled to when emitted
+pub fn set_color_depth(depth: ColorDepth) {
+    COLOR_DEPTH.store(depth as u8 + 1, Ordering::Relaxed);
+}
+
+/// The color depth colors are downsampled to when emitted
+///
+/// Detected from the environment with [`detect_color_depth`] unless set with [`set_color_depth`]
+#[must_use]
+pub fn color_depth() -> ColorDepth {
+    match COLOR_DEPTH.load(Ordering::Relaxed) {
+        1 => ColorDepth::Ansi16,
+        2 => ColorDepth::Ansi256,
+        3 => ColorDepth::TrueColor,
+        _ => {
+            let depth = detect_color_depth();
+            set_color_depth(depth);
+            depth
+        }
+    }
+}
+
+/// Detects the color depth of the terminal from the `COLORTERM`, `TERM` and `TERM_PROGRAM`
+/// environment variables
+///
+/// Terminals that support truecolor don't always say so, [`query_truecolor`] can confirm it
+#[must_use]
+pub fn detect_color_depth() -> ColorDepth {
+    detect_color_depth_from(|name| env::var(name).ok())
+}
+
+fn detect_color_depth_from(var: impl Fn(&str) -> Option<String>) -> ColorDepth {
+    const TRUECOLOR_TERMS: [&str; 8] = [
+        "truecolor",
+        "24bit",
+        "direct",
+        "kitty",
+        "alacritty",
+        "foot",
+        "wezterm",
+        "ghostty",
+    ];
+    const TRUECOLOR_PROGRAMS: [&str; 5] = ["iTerm.app", "WezTerm", "vscode", "ghostty", "Hyper"];
+
+    if var("COLORTERM").is_some_and(|c| c == "truecolor" || c == "24bit")
+        || var("TERM_PROGRAM").is_some_and(|p| TRUECOLOR_PROGRAMS.contains(&p.as_str()))
+        || var("WT_SESSION").is_some()
+    {
+        return ColorDepth::TrueColor;
+    }
+    match var("TERM") {
+        Some(term) if TRUECOLOR_TERMS.iter().any(|t| term.contains(t)) => ColorDepth::TrueColor,
+        Some(term) if term.contains("256") => ColorDepth::Ansi256,
+        // Windows 10+ consoles support truecolor but don't set TERM
+        None if cfg!(windows) => ColorDepth::TrueColor,
+        _ => ColorDepth::Ansi16,
+    }
+}
+
+/// Asks the terminal whether it supports truecolor, by setting a truecolor background and
+/// reading it back with DECRQSS
+///
+/// `Ok(false)` only means the terminal didn't confirm it, many terminals with truecolor don't
+/// support DECRQSS, this needs raw mode to be enabled and resets the current style
+///
+/// # Errors
+/// If the terminal can't be written to or read from or
+/// it didn't reply before the timeout
+pub fn query_truecolor(timeout: Duration) -> io::Result<bool> {
+    let reply = query("\x1b[48;2;1;2;3m\x1bP$qm\x1b\\\x1b[0m", timeout)?;
+    Ok(string_reply(&reply, "\x1bP1$r")
+        .is_some_and(|setting| setting.contains("1:2:3") || setting.contains("1;2;3")))
+}
+
+/// The named colors in palette order
+const NAMED: [(Color, &str); 16] = [
+    (Color::Black, "black"),
+    (Color::Red, "red"),
+    (Color::Green, "green"),
+    (Color::Yellow, "yellow"),
+    (Color::Blue, "blue"),
+    (Color::Magenta, "magenta"),
+    (Color::Cyan, "cyan"),
+    (Color::White, "white"),
+    (Color::BrightBlack, "brightblack"),
+    (Color::BrightRed, "brightred"),
+    (Color::BrightGreen, "brightgreen"),
+    (Color::BrightYellow, "brightyellow"),
+    (Color::BrightBlue, "brightblue"),
+    (Color::BrightMagenta, "brightmagenta"),
+    (Color::BrightCyan, "brightcyan"),
+    (Color::BrightWhite, "brightwhite"),
+];
+
+impl Color {
+    /// The palette index of a named color, 0-7 for normal and 8-15 for bright
+    #[must_use]
+    pub fn palette_index(self) -> Option<u8> {
+        match self {
+            Self::Indexed(index) => Some(index),
+            Self::Default | Self::Rgb(..) => None,
+            #[allow(clippy::cast_possible_truncation)]
+            named => NAMED.iter().position(|(c, _)| *c == named).map(|i| i as u8),
+        }
+    }
+
+    /// Converts to the nearest color available at `depth`, by perceptual distance
+    ///
+    /// Palette colors are assumed to have xterm's default values
+    #[must_use]
+    pub fn downsample(self, depth: ColorDepth) -> Self {
+        match (self, depth) {
+            (Self::Rgb(..), ColorDepth::Ansi256) => Self::Indexed(nearest_256(self.rgb())),
+            (Self::Indexed(index), ColorDepth::Ansi16) if index < 16 => NAMED[index as usize].0,
+            (Self::Rgb(..) | Self::Indexed(_), ColorDepth::Ansi16) => {
+                let rgb = self.rgb();
+                NAMED
+                    .iter()
+                    .zip(PALETTE_16)
+                    .min_by_key(|(_, palette)| distance(rgb, *palette))
+                    .map_or(self, |((color, _), _)| *color)
+            }
+            _ => self,
+        }
+    }
+
+    /// The approximate value of this color, `Default` is treated as black
+    pub(crate) fn rgb(self) -> (u8, u8, u8) {
+        match self {
+            Self::Rgb(red, green, blue) => (red, green, blue),
+            Self::Indexed(index @ 16..=231) => {
+                let index = index - 16;
+                let level = |i: u8| CUBE_LEVELS[usize::from(i)];
+                (level(index / 36), level(index / 6 % 6), level(index % 6))
+            }
+            Self::Indexed(index @ 232..) => {
+                let gray = 8 + (index - 232) * 10;
+                (gray, gray, gray)
+            }
+            color => PALETTE_16[usize::from(color.palette_index().unwrap_or(0))],
+        }
+    }
+
+    /// Sets the foreground to this color
+    #[must_use]
+    pub const fn fg(self) -> SetForeground {
+        SetForeground(self)
+    }
+
+    /// Sets the background to this color
+    #[must_use]
+    pub const fn bg(self) -> SetBackground {
+        SetBackground(self)
+    }
+
+    /// Sets the underline color to this color
+    #[must_use]
+    pub const fn underline(self) -> SetUnderlineColor {
+        SetUnderlineColor(self)
+    }
+
+    /// Writes the SGR parameters setting the underline color, which has no short form for named
+    /// colors
+    pub(crate) fn write_underline_sgr(self, f: &mut impl fmt::Write) -> fmt::Result {
+        match self {
+            Self::Default => f.write_str("59"),
+            Self::Rgb(red, green, blue) => write!(f, "58;2;{red};{green};{blue}"),
+            color => write!(f, "58;5;{}", color.palette_index().unwrap_or(0)),
+        }
+    }
+
+    /// Writes the SGR parameters of this color, `base` is 30 for foreground and 40 for background
+    pub(crate) fn write_sgr(self, f: &mut impl fmt::Write, base: u8) -> fmt::Result {
+        match self {
+            Self::Rgb(red, green, blue) => write!(f, "{};2;{red};{green};{blue}", base + 8),
+            Self::Indexed(index) => write!(f, "{};5;{index}", base + 8),
+            Self::Default => write!(f, "{}", base + 9),
+            named => {
+                let index = named.palette_index().unwrap_or(0);
+                if index < 8 {
+                    write!(f, "{}", base + index)
+                } else {
+                    write!(f, "{}", base + 60 + index - 8)
+                }
+            }
+        }
+    }
+}
+
+/// Parses an X11 color spec as used by terminal color queries, `rgb:r/g/b` with 1-4 hex digits
+/// per channel or `#rrggbb`
+pub(crate) fn parse_color_spec(spec: &str) -> Option<Color> {
+    if spec.starts_with('#') {
+        return spec.parse().ok();
+    }
+    let mut channels = spec.strip_prefix("rgb:")?.split('/').map(|channel| {
+        let value = u32::from_str_radix(channel, 16).ok()?;
+        let max = 16u32.checked_pow(u32::try_from(channel.len()).ok()?)? - 1;
+        (1..=4)
+            .contains(&channel.len())
+            .then(|| u8::try_from(value * 255 / max).ok())?
+    });
+    let color = Color::Rgb(channels.next()??, channels.next()??, channels.next()??);
+    channels.next().is_none().then_some(color)
+}
+
+/// xterm's default values for the named colors
+const PALETTE_16: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+/// The channel values of the 6x6x6 color cube in the 256 color palette
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Finds the nearest color in the cube or grayscale ramp of the 256 color palette
+fn nearest_256(rgb: (u8, u8, u8)) -> u8 {
+    let nearest_level = |channel: u8| {
+        (0..6u8)
+            .min_by_key(|&i| CUBE_LEVELS[usize::from(i)].abs_diff(channel))
+            .unwrap_or(0)
+    };
+    let (r, g, b) = (
+        nearest_level(rgb.0),
+        nearest_level(rgb.1),
+        nearest_level(rgb.2),
+    );
+    let cube = 16 + 36 * r + 6 * g + b;
+
+    let average = (u16::from(rgb.0) + u16::from(rgb.1) + u16::from(rgb.2)) / 3;
+    #[allow(clippy::cast_possible_truncation)]
+    let step = (average.saturating_sub(3) / 10).min(23) as u8;
+    let gray = 232 + step;
+
+    if distance(rgb, Color::Indexed(gray).rgb()) < distance(rgb, Color::Indexed(cube).rgb()) {
+        gray
+    } else {
+        cube
+    }
+}
+
+/// Perceptual distance between two colors, a "redmean" weighted euclidean distance
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let red_mean = u32::midpoint(u32::from(a.0), u32::from(b.0));
+    let red = u32::from(a.0.abs_diff(b.0)).pow(2);
+    let green = u32::from(a.1.abs_diff(b.1)).pow(2);
+    let blue = u32::from(a.2.abs_diff(b.2)).pow(2);
+    (((512 + red_mean) * red) >> 8) + 4 * green + (((767 - red_mean) * blue) >> 8)
+}
+
+/// The error returned when parsing a [`Color`] fails
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseColorError;
+
+impl fmt::Display for ParseColorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid color, expected #rrggbb, rgb(r, g, b), a name or 0-255")
+    }
+}
+
+impl Error for ParseColorError {}
+
+impl FromStr for Color {
+    type Err = ParseColorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some(hex) = s.strip_prefix('#') {
+            let digit = |i: usize| u8::from_str_radix(hex.get(i..=i)?, 16).ok();
+            let pair = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+            let rgb = match hex.len() {
+                3 => (|| Some(Self::Rgb(digit(0)? * 17, digit(1)? * 17, digit(2)? * 17)))(),
+                6 => (|| Some(Self::Rgb(pair(0)?, pair(2)?, pair(4)?)))(),
+                _ => None,
+            };
+            return rgb.ok_or(ParseColorError);
+        }
+        let lower = s.to_ascii_lowercase();
+        if let Some(args) = lower
+            .strip_prefix("rgb(")
+            .and_then(|rest| rest.strip_suffix(')'))
+        {
+            let mut channels = args.split(',').map(|c| c.trim().parse::<u8>());
+            return match (channels.next(), channels.next(), channels.next()) {
+                (Some(Ok(r)), Some(Ok(g)), Some(Ok(b))) if channels.next().is_none() => {
+                    Ok(Self::Rgb(r, g, b))
+                }
+                _ => Err(ParseColorError),
+            };
+        }
+        if let Ok(index) = lower.parse::<u8>() {
+            return Ok(Self::Indexed(index));
+        }
+        let name: String = lower
+            .chars()
+            .filter(|c| !matches!(c, '-' | '_' | ' '))
+            .collect();
+        if name == "default" {
+            return Ok(Self::Default);
+        }
+        NAMED
+            .iter()
+            .find(|(_, n)| *n == name)
+            .map(|(c, _)| *c)
+            .ok_or(ParseColorError)
+    }
+}
+
+/// Makes characters sent to the screen have a foreground color
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SetForeground(pub Color);
+
+/// Makes characters sent to the screen have a background color
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SetBackground(pub Color);
+
+/// Makes underlines of characters sent to the screen have a color
+///
+/// This is less commonly supported than foreground and background colors and is not sent unless
+/// [`styled_underlines`](crate::ansi::styled_underlines), named colors are sent as their palette
+/// index as there is no short form
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SetUnderlineColor(pub Color);
+
+impl Command for SetForeground {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        if !colors_enabled() {
+            return Ok(());
+        }
+        f.write_str("\x1b[")?;
+        self.0.downsample(color_depth()).write_sgr(f, 30)?;
+        f.write_char('m')
+    }
+}
+
+impl Command for SetBackground {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        if !colors_enabled() {
+            return Ok(());
+        }
+        f.write_str("\x1b[")?;
+        self.0.downsample(color_depth()).write_sgr(f, 40)?;
+        f.write_char('m')
+    }
+}
+
+impl Command for SetUnderlineColor {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        if !colors_enabled() {
+            return Ok(());
+        }
+        if !styled_underlines() {
+            return Ok(());
+        }
+        f.write_str("\x1b[")?;
+        self.0.downsample(color_depth()).write_underline_sgr(f)?;
+        f.write_char('m')
+    }
+}
+
+impl_display!(SetForeground, SetBackground, SetUnderlineColor);
+
+#[test]
+fn test_color() {
+    let parse = |s: &str| s.parse::<Color>();
+    assert_eq!(parse("#ff8000"), Ok(Color::Rgb(255, 128, 0)));
+    assert_eq!(parse("#F80"), Ok(Color::Rgb(255, 136, 0)));
+    assert_eq!(parse("RGB(1, 2,3)"), Ok(Color::Rgb(1, 2, 3)));
+    assert_eq!(parse("Bright_Red"), Ok(Color::BrightRed));
+    assert_eq!(parse("208"), Ok(Color::Indexed(208)));
+    assert_eq!(parse("rgb(1, 2, 300)"), Err(ParseColorError));
+    assert_eq!(parse("#12345"), Err(ParseColorError));
+
+    crate::policy::with_full_terminal(|| {
+        assert_eq!(Color::BrightCyan.fg().to_string(), "\x1b[96m");
+        assert_eq!(Color::Blue.bg().to_string(), "\x1b[44m");
+        assert_eq!(Color::Indexed(208).bg().to_string(), "\x1b[48;5;208m");
+        assert_eq!(Color::BrightRed.underline().to_string(), "\x1b[58;5;9m");
+        assert_eq!(Color::Default.underline().to_string(), "\x1b[59m");
+    });
+
+    assert_eq!(
+        parse_color_spec("rgb:ffff/8080/0"),
+        Some(Color::Rgb(255, 128, 0))
+    );
+    assert_eq!(parse_color_spec("#0080ff"), Some(Color::Rgb(0, 128, 255)));
+    assert_eq!(parse_color_spec("rgb:ff/ff"), None);
+}
+
+#[test]
+fn test_color_depth() {
+    let detect = |vars| detect_color_depth_from(crate::policy::env_from(vars));
+    assert_eq!(detect(&[("TERM", "linux")]), ColorDepth::Ansi16);
+    assert_eq!(detect(&[("TERM", "xterm-256color")]), ColorDepth::Ansi256);
+    assert_eq!(
+        detect(&[("TERM", "xterm-256color"), ("COLORTERM", "truecolor")]),
+        ColorDepth::TrueColor
+    );
+
+    let orange = Color::Rgb(255, 135, 0);
+    assert_eq!(orange.downsample(ColorDepth::TrueColor), orange);
+    assert_eq!(orange.downsample(ColorDepth::Ansi256), Color::Indexed(208));
+    assert_eq!(orange.downsample(ColorDepth::Ansi16), Color::Yellow);
+    let gray = Color::Rgb(100, 100, 102);
+    assert_eq!(gray.downsample(ColorDepth::Ansi256), Color::Indexed(241));
+    assert_eq!(
+        Color::Indexed(4).downsample(ColorDepth::Ansi16),
+        Color::Blue
+    );
+    assert_eq!(
+        Color::Indexed(46).downsample(ColorDepth::Ansi16),
+        Color::BrightGreen
+    );
+}
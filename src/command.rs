@@ -0,0 +1,680 @@
+use std::fmt;
+use std::io;
+
+use crate::policy::colors_enabled;
+
+/// An escape sequence that can be written to the terminal without allocating
+///
+/// Every command also implements [`Display`](fmt::Display) so it can be used with `print!` and
+/// `format!` like the string constants, and [`str`] implements it so the constants can be
+/// queued alongside typed commands
+///
+/// ```no_run
+/// use neutuino::prelude::*;
+/// use std::io;
+///
+/// let mut stdout = io::stdout().lock();
+/// neutuino::execute!(
+///     stdout,
+///     MoveCursorToPosition(4, 2),
+///     SetForeground(Color::Red),
+///     STYLE_BOLD,
+/// )?;
+/// # Ok::<(), io::Error>(())
+/// ```
+pub trait Command {
+    /// Writes the escape sequence to `f`
+    ///
+    /// # Errors
+    /// If writing to `f` fails
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result;
+
+    /// Writes the escape sequence to an [`io::Write`] without allocating
+    ///
+    /// # Errors
+    /// If writing to `writer` fails
+    fn write_to(&self, writer: &mut impl io::Write) -> io::Result<()> {
+        // Formatting inside write_fmt lets a ColorWriter apply its colors to the command
+        writer.write_fmt(format_args!("{}", Ansi(self)))
+    }
+}
+
+/// Formats a command as its escape sequence
+struct Ansi<'a, C: ?Sized>(&'a C);
+
+impl<C: Command + ?Sized> fmt::Display for Ansi<'_, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.write_ansi(f)
+    }
+}
+
+impl Command for str {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        f.write_str(self)
+    }
+}
+
+impl<T: Command + ?Sized> Command for &T {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        (**self).write_ansi(f)
+    }
+}
+
+/// Forwards formatted output to an [`io::Write`], keeping the io error that formatting discards
+struct Adapter<'a, W> {
+    inner: &'a mut W,
+    error: Option<io::Error>,
+}
+
+impl<W: io::Write> fmt::Write for Adapter<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.inner.write_all(s.as_bytes()).map_err(|e| {
+            self.error = Some(e);
+            fmt::Error
+        })
+    }
+}
+
+/// Same as the default [`io::Write::write_fmt`], for writers that override it
+pub(crate) fn write_fmt(writer: &mut impl io::Write, args: fmt::Arguments<'_>) -> io::Result<()> {
+    let mut adapter = Adapter {
+        inner: writer,
+        error: None,
+    };
+    fmt::write(&mut adapter, args).map_err(|fmt::Error| {
+        adapter
+            .error
+            .unwrap_or_else(|| io::Error::other("Could not format command"))
+    })
+}
+
+/// Writes commands to an [`io::Write`] without flushing
+///
+/// Evaluates to an [`io::Result<()>`](std::io::Result), stopping at the first error
+///
+/// Colors and styles follow stdout's [`ColorPolicy`](crate::ansi::ColorPolicy), or the writer's
+/// own colors if it is a [`ColorWriter`](crate::ansi::ColorWriter)
+#[macro_export]
+macro_rules! queue {
+    ($writer:expr $(, $command:expr)* $(,)?) => {
+        ::std::io::Result::Ok(())
+            $(.and_then(|()| $crate::ansi::Command::write_to(&$command, &mut $writer)))*
+    };
+}
+
+/// Writes commands to an [`io::Write`] then flushes it
+///
+/// Evaluates to an [`io::Result<()>`](std::io::Result), stopping at the first error
+#[macro_export]
+macro_rules! execute {
+    ($writer:expr $(, $command:expr)* $(,)?) => {
+        $crate::queue!($writer $(, $command)*)
+            .and_then(|()| ::std::io::Write::flush(&mut $writer))
+    };
+}
+
+/// Implements `Display` for commands by writing their escape sequence
+macro_rules! impl_display {
+    ($($command:ty),* $(,)?) => {
+        $(
+            impl ::std::fmt::Display for $command {
+                fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                    $crate::ansi::Command::write_ansi(self, f)
+                }
+            }
+        )*
+    };
+}
+
+pub(crate) use impl_display;
+
+/// Drops control characters written through it, so text from anywhere can go inside an escape
+/// sequence without ending it early
+pub(crate) struct StripControl<'a, W>(pub &'a mut W);
+
+impl<W: fmt::Write> fmt::Write for StripControl<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for part in s.split(char::is_control) {
+            self.0.write_str(part)?;
+        }
+        Ok(())
+    }
+}
+
+/// Moves the cursor up {num} characters
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MoveCursorUp(pub u16);
+
+/// Moves the cursor down {num} characters
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MoveCursorDown(pub u16);
+
+/// Moves the cursor right {num} characters
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MoveCursorRight(pub u16);
+
+/// Moves the cursor left {num} characters
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MoveCursorLeft(pub u16);
+
+/// Moves the cursor to {row}
+///
+/// Origin is 0, 0
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MoveCursorToRow(pub u16);
+
+/// Moves the cursor to {column}
+///
+/// Origin is 0, 0
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MoveCursorToColumn(pub u16);
+
+/// Moves the cursor to Position {column}, {row}
+///
+/// Origin is 0, 0
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MoveCursorToPosition(pub u16, pub u16);
+
+/// Saves the current cursor position
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SaveCursorPosition;
+
+/// Restores the saved cursor position
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct RestoreCursorPosition;
+
+impl Command for MoveCursorUp {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        write!(f, "\x1b[{}A", self.0)
+    }
+}
+
+impl Command for MoveCursorDown {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        write!(f, "\x1b[{}B", self.0)
+    }
+}
+
+impl Command for MoveCursorRight {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        write!(f, "\x1b[{}C", self.0)
+    }
+}
+
+impl Command for MoveCursorLeft {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        write!(f, "\x1b[{}D", self.0)
+    }
+}
+
+impl Command for MoveCursorToRow {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        write!(f, "\x1b[{}d", self.0.saturating_add(1))
+    }
+}
+
+impl Command for MoveCursorToColumn {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        write!(f, "\x1b[{}G", self.0.saturating_add(1))
+    }
+}
+
+impl Command for MoveCursorToPosition {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        write!(
+            f,
+            "\x1b[{};{}H",
+            self.1.saturating_add(1),
+            self.0.saturating_add(1)
+        )
+    }
+}
+
+impl Command for SaveCursorPosition {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        f.write_str("\x1b7")
+    }
+}
+
+impl Command for RestoreCursorPosition {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        f.write_str("\x1b8")
+    }
+}
+
+/// Enters the alternate screen
+///
+/// The alternate screen is a blank screen that won't interrupt the main screen (e.g. vi)
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct EnterAltScreen;
+
+/// Exits the alternate screen
+///
+/// The alternate screen is a blank screen that won't interrupt the main screen (e.g. vi)
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ExitAltScreen;
+
+impl Command for EnterAltScreen {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        f.write_str("\x1b[?1049h")
+    }
+}
+
+impl Command for ExitAltScreen {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        f.write_str("\x1b[?1049l")
+    }
+}
+
+/// Sets the shape of the cursor
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum CursorShape {
+    /// The user-specified default
+    #[default]
+    Default,
+    BlinkingBlock,
+    SteadyBlock,
+    BlinkingUnderline,
+    SteadyUnderline,
+    BlinkingBar,
+    SteadyBar,
+}
+
+impl Command for CursorShape {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        write!(f, "\x1b[{}q", *self as u8)
+    }
+}
+
+/// Erases part of the screen while leaving cursor in place
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Erase {
+    /// The entire screen
+    Screen,
+    /// The line the cursor is on
+    Line,
+    /// From the screen start to the cursor
+    CursorToScreenStart,
+    /// From the cursor to the screen end
+    CursorToScreenEnd,
+    /// From the line start to the cursor
+    CursorToLineStart,
+    /// From the cursor to the line end
+    CursorToLineEnd,
+}
+
+impl Command for Erase {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        f.write_str(match self {
+            Self::Screen => "\x1b[2J",
+            Self::Line => "\x1b[2K",
+            Self::CursorToScreenStart => "\x1b[1J",
+            Self::CursorToScreenEnd => "\x1b[0J",
+            Self::CursorToLineStart => "\x1b[1K",
+            Self::CursorToLineEnd => "\x1b[0K",
+        })
+    }
+}
+
+/// Restricts scrolling to the rows from {top} to {bottom} inclusive (DECSTBM)
+///
+/// Origin is 0, 0, this also moves the cursor to the top left of the screen
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SetScrollRegion(pub u16, pub u16);
+
+/// Lets the whole screen scroll again
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ResetScrollRegion;
+
+/// Scrolls the scroll region up {num} lines, adding blank lines at the bottom
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ScrollUp(pub u16);
+
+/// Scrolls the scroll region down {num} lines, adding blank lines at the top
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ScrollDown(pub u16);
+
+/// Inserts {num} blank lines at the cursor, pushing the lines below down the scroll region
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct InsertLines(pub u16);
+
+/// Deletes {num} lines at the cursor, pulling the lines below up the scroll region
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct DeleteLines(pub u16);
+
+/// Inserts {num} blank characters at the cursor, pushing the rest of the line right
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct InsertChars(pub u16);
+
+/// Deletes {num} characters at the cursor, pulling the rest of the line left
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct DeleteChars(pub u16);
+
+/// Erases {num} characters from the cursor while leaving cursor in place
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct EraseChars(pub u16);
+
+/// Repeats the last character sent to the screen {num} more times (REP)
+///
+/// This is less commonly supported than other editing commands
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct RepeatChar(pub u16);
+
+impl Command for SetScrollRegion {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        write!(
+            f,
+            "\x1b[{};{}r",
+            self.0.saturating_add(1),
+            self.1.saturating_add(1)
+        )
+    }
+}
+
+impl Command for ResetScrollRegion {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        f.write_str("\x1b[r")
+    }
+}
+
+impl Command for ScrollUp {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        write!(f, "\x1b[{}S", self.0)
+    }
+}
+
+impl Command for ScrollDown {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        write!(f, "\x1b[{}T", self.0)
+    }
+}
+
+impl Command for InsertLines {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        write!(f, "\x1b[{}L", self.0)
+    }
+}
+
+impl Command for DeleteLines {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        write!(f, "\x1b[{}M", self.0)
+    }
+}
+
+impl Command for InsertChars {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        write!(f, "\x1b[{}@", self.0)
+    }
+}
+
+impl Command for DeleteChars {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        write!(f, "\x1b[{}P", self.0)
+    }
+}
+
+impl Command for EraseChars {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        write!(f, "\x1b[{}X", self.0)
+    }
+}
+
+impl Command for RepeatChar {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        write!(f, "\x1b[{}b", self.0)
+    }
+}
+
+/// Sets a tab stop at the cursor's column
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SetTabStop;
+
+/// Clears the tab stop at the cursor's column
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ClearTabStop;
+
+/// Clears every tab stop
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ClearAllTabStops;
+
+/// Moves the cursor forward {num} tab stops
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MoveCursorToNextTab(pub u16);
+
+/// Moves the cursor back {num} tab stops
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MoveCursorToPreviousTab(pub u16);
+
+impl Command for SetTabStop {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        f.write_str("\x1bH")
+    }
+}
+
+impl Command for ClearTabStop {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        f.write_str("\x1b[0g")
+    }
+}
+
+impl Command for ClearAllTabStops {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        f.write_str("\x1b[3g")
+    }
+}
+
+impl Command for MoveCursorToNextTab {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        write!(f, "\x1b[{}I", self.0)
+    }
+}
+
+impl Command for MoveCursorToPreviousTab {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        write!(f, "\x1b[{}Z", self.0)
+    }
+}
+
+/// A text style that can be turned on and off
+///
+/// Like colors, attributes are only emitted if [`colors_enabled`](crate::ansi::colors_enabled)
+///
+/// Underline, blinking, reverse, hidden and strikethrough are less commonly supported
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Attribute {
+    Bold,
+    Dim,
+    Italic,
+    Underline,
+    Blinking,
+    Reverse,
+    Hidden,
+    Strikethrough,
+}
+
+impl Attribute {
+    pub(crate) const fn set_code(self) -> u8 {
+        match self {
+            Self::Bold => 1,
+            Self::Dim => 2,
+            Self::Italic => 3,
+            Self::Underline => 4,
+            Self::Blinking => 5,
+            Self::Reverse => 7,
+            Self::Hidden => 8,
+            Self::Strikethrough => 9,
+        }
+    }
+
+    pub(crate) const fn reset_code(self) -> u8 {
+        match self {
+            // There is no separate reset for bold, 21 is double underline on many terminals
+            Self::Bold | Self::Dim => 22,
+            attribute => attribute.set_code() + 20,
+        }
+    }
+}
+
+/// Makes characters sent to the screen have an attribute
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SetAttribute(pub Attribute);
+
+/// Stops characters sent to the screen from having an attribute
+///
+/// Resetting bold also resets dim and vice versa
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ResetAttribute(pub Attribute);
+
+/// Resets all styles and colors
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ResetStyle;
+
+impl Command for SetAttribute {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        if !colors_enabled() {
+            return Ok(());
+        }
+        write!(f, "\x1b[{}m", self.0.set_code())
+    }
+}
+
+impl Command for ResetAttribute {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        if !colors_enabled() {
+            return Ok(());
+        }
+        write!(f, "\x1b[{}m", self.0.reset_code())
+    }
+}
+
+impl Command for ResetStyle {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        if !colors_enabled() {
+            return Ok(());
+        }
+        f.write_str("\x1b[0m")
+    }
+}
+
+/// Sets the title of the window
+///
+/// Control characters in the title are dropped as they could end the escape sequence early
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SetWindowTitle<T>(pub T);
+
+impl<T: fmt::Display> Command for SetWindowTitle<T> {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        f.write_str("\x1b]0;")?;
+        fmt::Write::write_fmt(&mut StripControl(f), format_args!("{}", self.0))?;
+        f.write_str("\x1b\x5c")
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for SetWindowTitle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_ansi(f)
+    }
+}
+
+/// Enables kitty comprehensive keyboard handling protocol
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct EnableKittyKeyboard;
+
+/// Disables kitty comprehensive keyboard handling protocol
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct DisableKittyKeyboard;
+
+/// Enables *nix mouse reporting
+///
+/// Windows consoles report the mouse without escape sequences, use
+/// [`enable_mouse_input`](crate::control::enable_mouse_input) to support both
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct EnableMouseInput;
+
+/// Disables *nix mouse reporting
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct DisableMouseInput;
+
+impl Command for EnableKittyKeyboard {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        f.write_str("\x1b[>31u")
+    }
+}
+
+impl Command for DisableKittyKeyboard {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        f.write_str("\x1b[<31u")
+    }
+}
+
+impl Command for EnableMouseInput {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        f.write_str("\x1b[?1000h\x1b[?1002h\x1b[?1015h\x1b[?1006h\x1b[?1003h")
+    }
+}
+
+impl Command for DisableMouseInput {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        f.write_str("\x1b[?1006l\x1b[?1015l\x1b[?1002l\x1b[?1000l\x1b[?1003l")
+    }
+}
+
+impl_display!(
+    MoveCursorUp,
+    MoveCursorDown,
+    MoveCursorRight,
+    MoveCursorLeft,
+    MoveCursorToRow,
+    MoveCursorToColumn,
+    MoveCursorToPosition,
+    SaveCursorPosition,
+    RestoreCursorPosition,
+    EnterAltScreen,
+    ExitAltScreen,
+    CursorShape,
+    Erase,
+    SetScrollRegion,
+    ResetScrollRegion,
+    ScrollUp,
+    ScrollDown,
+    InsertLines,
+    DeleteLines,
+    InsertChars,
+    DeleteChars,
+    EraseChars,
+    RepeatChar,
+    SetTabStop,
+    ClearTabStop,
+    ClearAllTabStops,
+    MoveCursorToNextTab,
+    MoveCursorToPreviousTab,
+    SetAttribute,
+    ResetAttribute,
+    ResetStyle,
+    EnableKittyKeyboard,
+    DisableKittyKeyboard,
+    EnableMouseInput,
+    DisableMouseInput,
+);
+
+#[test]
+fn test_queue() {
+    use crate::ansi::{Color, STYLE_BOLD, SetForeground};
+
+    let mut buf = Vec::new();
+    crate::policy::with_colors(true, || {
+        crate::queue!(
+            buf,
+            MoveCursorToPosition(4, 2),
+            SetForeground(Color::Red),
+            STYLE_BOLD,
+            CursorShape::SteadyBar,
+            ResetAttribute(Attribute::Bold),
+        )
+    })
+    .unwrap();
+    assert_eq!(buf, b"\x1b[3;5H\x1b[31m\x1b[1m\x1b[6q\x1b[22m");
+    assert_eq!(MoveCursorLeft(3).to_string(), "\x1b[3D");
+    assert_eq!(SetScrollRegion(1, 10).to_string(), "\x1b[2;11r");
+    assert_eq!(DeleteChars(2).to_string(), "\x1b[2P");
+}
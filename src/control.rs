@@ -4,6 +4,12 @@
 
 use std::io;
 
+pub use crate::cursor::{CursorState, restore_cursor_state, save_cursor_state};
+pub use crate::grapheme::{disable_grapheme_clusters, enable_grapheme_clusters};
+pub use crate::palette::{Palette, restore_palette, save_palette};
+pub use crate::progress::update_progress;
+pub use crate::window::{get_cell_pixel_size, get_terminal_pixel_size};
+
 #[cfg(unix)]
 pub use crate::unix::{
     disable_ansi, disable_mouse_input, disable_raw_mode, enable_mouse_input, enable_raw_mode,
@@ -16,39 +22,68 @@ pub use crate::windows::{
     get_terminal_size,
 };
 
-const ENABLE_KITTY_KEYBOARD: &str = "\x1b[>31u";
-const DISABLE_KITTY_KEYBOARD: &str = "\x1b[<31u";
-
 /// Enable kitty comprehensive keyboard handling protocol
 pub fn enable_kitty_keyboard() {
-    print!("{ENABLE_KITTY_KEYBOARD}");
+    print!("{EnableKittyKeyboard}");
 }
 
 /// Disable kitty comprehensive keyboard handling protocol
 pub fn disable_kitty_keyboard() {
-    print!("{DISABLE_KITTY_KEYBOARD}");
+    print!("{DisableKittyKeyboard}");
 }
 
-use crate::prelude::{ALT_SCREEN_ENTER, ALT_SCREEN_EXIT, enable_ansi};
+use crate::ansi::{
+    DisableKittyKeyboard, EnableKittyKeyboard, EnterAltScreen, ExitAltScreen, enable_ansi,
+};
+use crate::progress::clear_progress;
 
+/// Prepares the terminal for a full-screen TUI application
+///
+/// Enables ANSI, raw mode, mouse input and kitty keyboard handling then enters the alternate
+/// screen, call [`save_cursor_state`] after it to have the cursor visibility and shape restored
+///
+/// # Errors
+///
+/// If enabling ANSI, raw mode or mouse input fails
 pub fn tui_init() -> io::Result<()> {
     enable_ansi()?;
     enable_raw_mode()?;
     enable_mouse_input()?;
-    print!("{ALT_SCREEN_ENTER}");
+    print!("{EnterAltScreen}");
     enable_kitty_keyboard();
     Ok(())
 }
 
+/// Restores the terminal after [`tui_init`], including the palette and cursor if they were saved
+///
+/// # Errors
+///
+/// If restoring the palette or cursor or disabling ANSI, raw mode or mouse input fails, every
+/// step is still attempted and the first error is returned
 pub fn tui_deinit() -> io::Result<()> {
+    disable_grapheme_clusters();
+    clear_progress();
     disable_kitty_keyboard();
-    print!("{ALT_SCREEN_EXIT}");
-    disable_mouse_input()?;
-    disable_raw_mode()?;
-    disable_ansi()?;
-    Ok(())
+    print!("{ExitAltScreen}");
+    // Collecting returns the first error once every step has run
+    [
+        restore_palette(),
+        restore_cursor_state(),
+        disable_mouse_input(),
+        disable_raw_mode(),
+        disable_ansi(),
+    ]
+    .into_iter()
+    .collect()
 }
 
+/// Prepares the terminal for an inline CLI application
+///
+/// Same as [`tui_init`] but stays on the main screen
+///
+/// # Errors
+///
+/// If enabling ANSI, raw mode or mouse input fails
 pub fn cli_init() -> io::Result<()> {
     enable_ansi()?;
     enable_raw_mode()?;
@@ -57,10 +92,24 @@ pub fn cli_init() -> io::Result<()> {
     Ok(())
 }
 
+/// Restores the terminal after [`cli_init`]
+///
+/// # Errors
+///
+/// If restoring the palette or cursor or disabling ANSI, raw mode or mouse input fails, every
+/// step is still attempted and the first error is returned
 pub fn cli_deinit() -> io::Result<()> {
-    disable_ansi()?;
-    disable_raw_mode()?;
-    disable_mouse_input()?;
+    disable_grapheme_clusters();
+    clear_progress();
+    let result = [
+        restore_palette(),
+        restore_cursor_state(),
+        disable_ansi(),
+        disable_raw_mode(),
+        disable_mouse_input(),
+    ]
+    .into_iter()
+    .collect();
     disable_kitty_keyboard();
-    Ok(())
+    result
 }
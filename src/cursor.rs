@@ -0,0 +1,208 @@
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use crate::color::{Color, parse_color_spec};
+use crate::command::{Command, CursorShape, impl_display};
+use crate::query::{mode_reply, query, string_reply};
+
+/// Shows the cursor
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ShowCursor;
+
+/// Hides the cursor
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct HideCursor;
+
+/// Sets the color of the cursor
+///
+/// Palette colors are sent as xterm's default values, [`Color::Default`] resets it
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SetCursorColor(pub Color);
+
+/// Resets the color of the cursor to the user-specified default
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ResetCursorColor;
+
+impl Command for ShowCursor {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        f.write_str("\x1b[?25h")
+    }
+}
+
+impl Command for HideCursor {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        f.write_str("\x1b[?25l")
+    }
+}
+
+impl Command for SetCursorColor {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        if self.0 == Color::Default {
+            return ResetCursorColor.write_ansi(f);
+        }
+        let (red, green, blue) = self.0.rgb();
+        write!(f, "\x1b]12;rgb:{red:02x}/{green:02x}/{blue:02x}\x1b\\")
+    }
+}
+
+impl Command for ResetCursorColor {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        f.write_str("\x1b]112\x1b\\")
+    }
+}
+
+/// The visibility, shape and color of the cursor
+///
+/// Writing it as a command restores it
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct CursorState {
+    pub visible: bool,
+    pub shape: CursorShape,
+    /// [`Color::Default`] if the terminal didn't report the color
+    pub color: Color,
+}
+
+impl Default for CursorState {
+    fn default() -> Self {
+        Self {
+            visible: true,
+            shape: CursorShape::Default,
+            color: Color::Default,
+        }
+    }
+}
+
+impl CursorState {
+    /// Asks the terminal for the state of the cursor, anything the terminal doesn't report is
+    /// assumed to be the default
+    ///
+    /// This needs raw mode to be enabled
+    ///
+    /// # Errors
+    /// If the terminal can't be written to or read from or
+    /// it didn't reply before the timeout
+    pub fn query(timeout: Duration) -> io::Result<Self> {
+        let reply = query("\x1b[?25$p\x1bP$q q\x1b\\\x1b]12;?\x1b\\", timeout)?;
+        Ok(Self::from_reply(&reply))
+    }
+
+    fn from_reply(reply: &[u8]) -> Self {
+        let visible = !matches!(mode_reply(reply, 25), Some(2 | 4));
+        let shape = string_reply(reply, "\x1bP1$r")
+            .and_then(|setting| setting.strip_suffix(" q")?.parse::<u8>().ok())
+            .map_or(CursorShape::Default, |shape| match shape {
+                1 => CursorShape::BlinkingBlock,
+                2 => CursorShape::SteadyBlock,
+                3 => CursorShape::BlinkingUnderline,
+                4 => CursorShape::SteadyUnderline,
+                5 => CursorShape::BlinkingBar,
+                6 => CursorShape::SteadyBar,
+                _ => CursorShape::Default,
+            });
+        let color = string_reply(reply, "\x1b]12;")
+            .and_then(parse_color_spec)
+            .unwrap_or(Color::Default);
+        Self {
+            visible,
+            shape,
+            color,
+        }
+    }
+}
+
+impl Command for CursorState {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        if self.visible {
+            ShowCursor.write_ansi(f)?;
+        } else {
+            HideCursor.write_ansi(f)?;
+        }
+        self.shape.write_ansi(f)?;
+        SetCursorColor(self.color).write_ansi(f)
+    }
+}
+
+impl_display!(
+    ShowCursor,
+    HideCursor,
+    SetCursorColor,
+    ResetCursorColor,
+    CursorState
+);
+
+static SAVED_CURSOR_STATE: Mutex<Option<CursorState>> = Mutex::new(None);
+
+/// Saves the state of the cursor to be restored by [`restore_cursor_state`], call this before
+/// changing it
+///
+/// This needs raw mode so call it after [`tui_init`](crate::control::tui_init) or
+/// [`cli_init`](crate::control::cli_init), if the terminal doesn't reply the default state is
+/// saved
+///
+/// Saving is not done by the init functions, as the query blocks until the terminal replies or
+/// `timeout` passes and terminals that never reply always wait the whole timeout
+///
+/// The color is saved as [`Color::Default`] so it is restored with a reset, a reported color can't
+/// be told apart from the user's default and setting it explicitly would stop it following their
+/// theme
+///
+/// # Errors
+/// If the terminal can't be written to or read from
+pub fn save_cursor_state(timeout: Duration) -> io::Result<()> {
+    let state = match CursorState::query(timeout) {
+        Ok(state) => CursorState {
+            color: Color::Default,
+            ..state
+        },
+        Err(e) if e.kind() == io::ErrorKind::TimedOut => CursorState::default(),
+        Err(e) => return Err(e),
+    };
+    *SAVED_CURSOR_STATE
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner) = Some(state);
+    Ok(())
+}
+
+/// Restores the state of the cursor saved by [`save_cursor_state`], does nothing if none was saved
+///
+/// Done by [`tui_deinit`](crate::control::tui_deinit) and
+/// [`cli_deinit`](crate::control::cli_deinit)
+///
+/// # Errors
+/// If stdout can't be written to
+pub fn restore_cursor_state() -> io::Result<()> {
+    let Some(state) = SAVED_CURSOR_STATE
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .take()
+    else {
+        return Ok(());
+    };
+    let mut stdout = io::stdout().lock();
+    state.write_to(&mut stdout)?;
+    stdout.flush()
+}
+
+#[test]
+fn test_cursor_state() {
+    let reply = b"\x1b[?25;2$y\x1bP1$r4 q\x1b\\\x1b]12;rgb:ffff/0000/8080\x1b\\";
+    assert_eq!(
+        CursorState::from_reply(reply),
+        CursorState {
+            visible: false,
+            shape: CursorShape::SteadyUnderline,
+            color: Color::Rgb(255, 0, 128),
+        }
+    );
+    assert_eq!(CursorState::from_reply(b""), CursorState::default());
+    assert_eq!(
+        CursorState::default().to_string(),
+        "\x1b[?25h\x1b[0q\x1b]112\x1b\\"
+    );
+    assert_eq!(
+        SetCursorColor(Color::Rgb(1, 2, 255)).to_string(),
+        "\x1b]12;rgb:01/02/ff\x1b\\"
+    );
+}
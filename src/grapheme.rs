@@ -0,0 +1,259 @@
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use crate::command::{Command, impl_display};
+use crate::grapheme_tables::GRAPHEME_BREAKS;
+use crate::query::query_private_mode;
+use crate::width::char_width;
+
+/// The grapheme cluster break property from UAX #29
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum GraphemeBreak {
+    Other,
+    Cr,
+    Lf,
+    Control,
+    Extend,
+    Zwj,
+    RegionalIndicator,
+    Prepend,
+    SpacingMark,
+    L,
+    V,
+    T,
+    Lv,
+    Lvt,
+    ExtendedPictographic,
+}
+
+impl GraphemeBreak {
+    fn of(c: char) -> Self {
+        match c {
+            '\r' => Self::Cr,
+            '\n' => Self::Lf,
+            c if c.is_ascii_control() => Self::Control,
+            c if c.is_ascii() => Self::Other,
+            // Hangul syllables are LV every 28 characters and LVT in between
+            '\u{AC00}'..='\u{D7A3}' if (u32::from(c) - 0xAC00) % 28 == 0 => Self::Lv,
+            '\u{AC00}'..='\u{D7A3}' => Self::Lvt,
+            c => {
+                let c = u32::from(c);
+                let index = GRAPHEME_BREAKS.partition_point(|&(_, end, _)| end < c);
+                match GRAPHEME_BREAKS.get(index) {
+                    Some(&(start, _, property)) if start <= c => property,
+                    _ => Self::Other,
+                }
+            }
+        }
+    }
+}
+
+/// An iterator over the grapheme clusters of a string, what the user sees as a single character
+///
+/// Created by [`graphemes`]
+#[derive(Debug, Clone)]
+pub struct Graphemes<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Graphemes<'a> {
+    /// The part of the string that hasn't been iterated over yet
+    #[must_use]
+    pub const fn as_str(&self) -> &'a str {
+        self.rest
+    }
+}
+
+impl<'a> Iterator for Graphemes<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        let mut chars = self.rest.char_indices();
+        let (_, first) = chars.next()?;
+        let mut prev = GraphemeBreak::of(first);
+        // How many regional indicators in a row end at `prev`
+        let mut regional_indicators = usize::from(prev == GraphemeBreak::RegionalIndicator);
+        // Whether an extended pictographic followed by extends ends at `prev`
+        let mut pictographic = prev == GraphemeBreak::ExtendedPictographic;
+        let mut zwj_after_pictographic = false;
+
+        let end = chars
+            .find(|&(_, c)| {
+                use GraphemeBreak as G;
+
+                let next = G::of(c);
+                let join = match (prev, next) {
+                    (G::Cr, G::Lf) => true,
+                    (G::Control | G::Cr | G::Lf, _) | (_, G::Control | G::Cr | G::Lf) => false,
+                    (G::L, G::L | G::V | G::Lv | G::Lvt)
+                    | (G::Lv | G::V, G::V | G::T)
+                    | (G::Lvt | G::T, G::T)
+                    | (_, G::Extend | G::Zwj | G::SpacingMark)
+                    | (G::Prepend, _) => true,
+                    (G::Zwj, G::ExtendedPictographic) => zwj_after_pictographic,
+                    (G::RegionalIndicator, G::RegionalIndicator) => regional_indicators % 2 == 1,
+                    _ => false,
+                };
+                regional_indicators = if next == G::RegionalIndicator {
+                    regional_indicators + 1
+                } else {
+                    0
+                };
+                zwj_after_pictographic = next == G::Zwj && pictographic;
+                pictographic =
+                    next == G::ExtendedPictographic || (next == G::Extend && pictographic);
+                prev = next;
+                !join
+            })
+            .map_or(self.rest.len(), |(i, _)| i);
+
+        let (grapheme, rest) = self.rest.split_at(end);
+        self.rest = rest;
+        Some(grapheme)
+    }
+}
+
+/// Splits a string into grapheme clusters following the extended grapheme cluster rules of
+/// UAX #29
+///
+/// Move the cursor and delete by grapheme clusters so accents, flags and emoji sequences aren't
+/// split apart, the property tables are generated from Unicode 14 by
+/// `scripts/grapheme_tables.py`
+///
+/// ```
+/// use neutuino::ansi::graphemes;
+///
+/// let clusters: Vec<&str> = graphemes("e\u{301}🇳🇱👩‍💻").collect();
+/// assert_eq!(clusters, ["e\u{301}", "🇳🇱", "👩‍💻"]);
+/// ```
+#[must_use]
+pub const fn graphemes(s: &str) -> Graphemes<'_> {
+    Graphemes { rest: s }
+}
+
+static GRAPHEME_CLUSTERS: AtomicBool = AtomicBool::new(false);
+/// Whether grapheme cluster mode was turned on by [`enable_grapheme_clusters`] rather than
+/// already being on
+static ENABLED_GRAPHEME_CLUSTERS: AtomicBool = AtomicBool::new(false);
+
+/// Sets whether the terminal measures grapheme clusters as a whole (mode 2027) rather than
+/// adding up the width of each character
+///
+/// [`enable_grapheme_clusters`] sets this if the terminal supports it
+pub fn set_grapheme_clusters(enabled: bool) {
+    GRAPHEME_CLUSTERS.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether the terminal measures grapheme clusters as a whole, see [`set_grapheme_clusters`]
+#[must_use]
+pub fn grapheme_clusters() -> bool {
+    GRAPHEME_CLUSTERS.load(Ordering::Relaxed)
+}
+
+/// The number of columns a grapheme cluster takes up in the terminal
+///
+/// Without [`grapheme_clusters`] this is the width of each character added up like most terminals
+/// do, with it the width of the first character or 2 for emoji presentation and flags
+#[must_use]
+pub fn grapheme_width(grapheme: &str) -> usize {
+    grapheme_width_in(grapheme, grapheme_clusters())
+}
+
+/// [`grapheme_width`] with grapheme cluster mode given rather than read
+pub(crate) fn grapheme_width_in(grapheme: &str, clusters: bool) -> usize {
+    if !clusters {
+        return grapheme.chars().map(char_width).sum();
+    }
+    let mut chars = grapheme.chars();
+    let Some(first) = chars.next() else {
+        return 0;
+    };
+    let emoji_presentation = chars.as_str().contains('\u{FE0F}');
+    let flag = GraphemeBreak::of(first) == GraphemeBreak::RegionalIndicator
+        && chars
+            .next()
+            .is_some_and(|c| GraphemeBreak::of(c) == GraphemeBreak::RegionalIndicator);
+    if emoji_presentation || flag {
+        2
+    } else {
+        char_width(first)
+    }
+}
+
+/// Turns on grapheme cluster mode (mode 2027) if the terminal supports it, making it measure
+/// grapheme clusters as a whole
+///
+/// Returns whether the mode is on, which sets [`set_grapheme_clusters`], this needs raw mode so
+/// call it after [`tui_init`](crate::control::tui_init) or [`cli_init`](crate::control::cli_init)
+/// which turn it off again when deinitialized
+///
+/// # Errors
+/// If the terminal can't be queried, a terminal that doesn't reply isn't an error
+pub fn enable_grapheme_clusters(timeout: Duration) -> io::Result<bool> {
+    let enabled = match query_private_mode(2027, timeout) {
+        Ok(1 | 3) => true,
+        Ok(2) => {
+            let mut stdout = io::stdout().lock();
+            EnableGraphemeClusters.write_to(&mut stdout)?;
+            stdout.flush()?;
+            ENABLED_GRAPHEME_CLUSTERS.store(true, Ordering::Relaxed);
+            true
+        }
+        Ok(_) => false,
+        Err(e) if e.kind() == io::ErrorKind::TimedOut => false,
+        Err(e) => return Err(e),
+    };
+    set_grapheme_clusters(enabled);
+    Ok(enabled)
+}
+
+/// Turns grapheme cluster mode back off if it was turned on by [`enable_grapheme_clusters`]
+pub fn disable_grapheme_clusters() {
+    if ENABLED_GRAPHEME_CLUSTERS.swap(false, Ordering::Relaxed) {
+        print!("{DisableGraphemeClusters}");
+        set_grapheme_clusters(false);
+    }
+}
+
+/// Makes the terminal measure grapheme clusters as a whole (mode 2027)
+///
+/// Not every terminal supports this, [`enable_grapheme_clusters`] checks first
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct EnableGraphemeClusters;
+
+/// Makes the terminal measure each character separately again
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct DisableGraphemeClusters;
+
+impl Command for EnableGraphemeClusters {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        f.write_str("\x1b[?2027h")
+    }
+}
+
+impl Command for DisableGraphemeClusters {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        f.write_str("\x1b[?2027l")
+    }
+}
+
+impl_display!(EnableGraphemeClusters, DisableGraphemeClusters);
+
+#[test]
+fn test_graphemes() {
+    let split = |s| graphemes(s).collect::<Vec<_>>();
+    assert_eq!(split("ab\r\n\n"), ["a", "b", "\r\n", "\n"]);
+    assert_eq!(split("🇳🇱🇧🇪🇩"), ["🇳🇱", "🇧🇪", "🇩"]);
+    assert_eq!(split("👨‍👩‍👧!"), ["👨‍👩‍👧", "!"]);
+    assert_eq!(split("a\u{200D}🦀"), ["a\u{200D}", "🦀"]);
+    assert_eq!(split("👍🏽x"), ["👍🏽", "x"]);
+    assert_eq!(
+        split("\u{1100}\u{1161}\u{11A8}한"),
+        ["\u{1100}\u{1161}\u{11A8}", "한"]
+    );
+    assert_eq!(split("नमस्ते"), ["न", "म", "स्", "ते"]);
+    assert_eq!(split("\u{600}1"), ["\u{600}1"]);
+    assert_eq!(split(""), Vec::<&str>::new());
+}
@@ -0,0 +1,191 @@
+use std::env;
+use std::fmt;
+use std::io::{self, IsTerminal};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use crate::command::{Command, impl_display};
+
+/// 0 until decided, then 1 for disabled and 2 for enabled
+static HYPERLINKS: AtomicU8 = AtomicU8::new(0);
+
+/// Sets whether the hyperlink commands emit anything
+pub fn set_hyperlinks(enabled: bool) {
+    HYPERLINKS.store(if enabled { 2 } else { 1 }, Ordering::Relaxed);
+}
+
+/// Whether the hyperlink commands emit anything
+///
+/// Unless set with [`set_hyperlinks`] this is whether stdout is a terminal other than `TERM=dumb`,
+/// `NO_COLOR` doesn't turn them off as they aren't colors
+#[must_use]
+pub fn hyperlinks_enabled() -> bool {
+    match HYPERLINKS.load(Ordering::Relaxed) {
+        1 => false,
+        2 => true,
+        _ => {
+            let dumb = env::var("TERM").is_ok_and(|term| term == "dumb");
+            let enabled = !dumb && io::stdout().is_terminal();
+            set_hyperlinks(enabled);
+            enabled
+        }
+    }
+}
+
+/// Starts a hyperlink, text sent to the screen until [`EndHyperlink`] can be clicked to open the
+/// URI
+///
+/// Cells with the same `id` and URI are highlighted together on hover, which is useful when a link
+/// is split across lines, the URI is percent-encoded and the id stripped of anything that could
+/// end the escape sequence early so both can come from untrusted input
+///
+/// Hyperlinks are only sent if [`hyperlinks_enabled`], text in a terminal without them just isn't
+/// clickable
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct StartHyperlink<'a> {
+    pub uri: &'a str,
+    pub id: Option<&'a str>,
+}
+
+impl<'a> StartHyperlink<'a> {
+    #[must_use]
+    pub const fn new(uri: &'a str) -> Self {
+        Self { uri, id: None }
+    }
+
+    #[must_use]
+    pub const fn id(self, id: &'a str) -> Self {
+        Self {
+            id: Some(id),
+            ..self
+        }
+    }
+}
+
+/// Ends the hyperlink started by [`StartHyperlink`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct EndHyperlink;
+
+impl Command for StartHyperlink<'_> {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        if !hyperlinks_enabled() {
+            return Ok(());
+        }
+        f.write_str("\x1b]8;")?;
+        if let Some(id) = self.id {
+            f.write_str("id=")?;
+            for c in id
+                .chars()
+                .filter(|c| c.is_ascii_graphic() && !matches!(c, ':' | ';'))
+            {
+                f.write_char(c)?;
+            }
+        }
+        f.write_char(';')?;
+        write_uri(f, self.uri)?;
+        f.write_str("\x1b\\")
+    }
+}
+
+impl Command for EndHyperlink {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        if !hyperlinks_enabled() {
+            return Ok(());
+        }
+        f.write_str("\x1b]8;;\x1b\\")
+    }
+}
+
+/// Writes a URI, percent-encoding the bytes OSC 8 doesn't allow (anything outside 32-126)
+fn write_uri(f: &mut impl fmt::Write, uri: &str) -> fmt::Result {
+    for part in uri.split_inclusive(|c: char| !matches!(c, ' '..='~')) {
+        let mut chars = part.chars();
+        match chars.next_back() {
+            Some(c) if !matches!(c, ' '..='~') => {
+                f.write_str(chars.as_str())?;
+                for byte in c.encode_utf8(&mut [0; 4]).bytes() {
+                    write!(f, "%{byte:02X}")?;
+                }
+            }
+            _ => f.write_str(part)?,
+        }
+    }
+    Ok(())
+}
+
+/// Text that is a hyperlink when printed
+///
+/// ```no_run
+/// use neutuino::ansi::Link;
+///
+/// let path = "/tmp/report.txt";
+/// println!("wrote {}", Link::new(path, format!("file://{path}")));
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Link<T, U, I = &'static str> {
+    pub text: T,
+    pub uri: U,
+    pub id: Option<I>,
+}
+
+impl<T: fmt::Display, U: AsRef<str>> Link<T, U> {
+    #[must_use]
+    pub const fn new(text: T, uri: U) -> Self {
+        Self {
+            text,
+            uri,
+            id: None,
+        }
+    }
+}
+
+impl<T: fmt::Display, U: AsRef<str>, I: AsRef<str>> Link<T, U, I> {
+    /// Sets the id grouping this link with others, see [`StartHyperlink`]
+    #[must_use]
+    pub fn id<J: AsRef<str>>(self, id: J) -> Link<T, U, J> {
+        Link {
+            text: self.text,
+            uri: self.uri,
+            id: Some(id),
+        }
+    }
+}
+
+impl<T: fmt::Display, U: AsRef<str>, I: AsRef<str>> Command for Link<T, U, I> {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        let start = StartHyperlink {
+            uri: self.uri.as_ref(),
+            id: self.id.as_ref().map(AsRef::as_ref),
+        };
+        start.write_ansi(f)?;
+        write!(f, "{}", self.text)?;
+        EndHyperlink.write_ansi(f)
+    }
+}
+
+impl<T: fmt::Display, U: AsRef<str>, I: AsRef<str>> fmt::Display for Link<T, U, I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_ansi(f)
+    }
+}
+
+impl_display!(StartHyperlink<'_>, EndHyperlink);
+
+#[test]
+fn test_hyperlink() {
+    crate::policy::with_full_terminal(|| {
+        assert_eq!(
+            StartHyperlink::new("https://example.com/a b").to_string(),
+            "\x1b]8;;https://example.com/a b\x1b\\"
+        );
+        assert_eq!(
+            StartHyperlink::new("file:///tmp/\x1b\\\x07é")
+                .id("x;y:\x1bz")
+                .to_string(),
+            "\x1b]8;id=xyz;file:///tmp/%1B\\%07%C3%A9\x1b\\"
+        );
+        assert_eq!(
+            Link::new("docs", "https://example.com").id("1").to_string(),
+            "\x1b]8;id=1;https://example.com\x1b\\docs\x1b]8;;\x1b\\"
+        );
+    });
+}
@@ -7,12 +7,67 @@
 //!
 //! In general the best support will be on Kitty-like linux terminals and Windows, due to historical
 //! reasons input on normal *nix terminals are limited
+//!
+//! # Escape key
+//! Legacy *nix terminals send the Escape key as a lone ESC byte, which is also the first byte of
+//! every escape sequence and of Alt+key, so the only way to tell them apart is timing
+//!
+//! After an ESC the reader waits up to [`escape_timeout`] (like vim's `ttimeoutlen`) for more
+//! bytes, if none arrive it is the Escape key, otherwise the bytes are decoded together
+//! - Too short and Alt+key or sequences split over a slow connection (e.g. SSH) are read as
+//!   Escape followed by a key
+//! - Too long and pressing Escape then a key quickly is read as Alt+key, and a lone Escape is
+//!   delayed by the timeout
+//!
+//! Only the wait after a bare ESC uses this timeout, the rest of a sequence is read with a short
+//! fixed timeout so raising it doesn't slow down other keys
+//!
+//! With kitty comprehensive keyboard handling enabled Escape and Alt+key are sent as
+//! unambiguous sequences, so the timeout is never waited for
+//!
+//! On Windows keys are delivered already decoded and the timeout is not used
+
+use std::io;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+
+/// The default for [`escape_timeout`]
+pub const DEFAULT_ESCAPE_TIMEOUT: Duration = Duration::from_millis(50);
+
+#[allow(clippy::cast_possible_truncation)]
+static ESCAPE_TIMEOUT_MICROS: AtomicU64 = AtomicU64::new(DEFAULT_ESCAPE_TIMEOUT.as_micros() as u64);
+
+/// Sets how long to wait after a bare ESC byte before reading it as the Escape key
+///
+/// See the [module documentation](self#escape-key) for the tradeoffs
+pub fn set_escape_timeout(timeout: Duration) {
+    let micros = u64::try_from(timeout.as_micros()).unwrap_or(u64::MAX);
+    ESCAPE_TIMEOUT_MICROS.store(micros, Ordering::Relaxed);
+}
+
+/// How long to wait after a bare ESC byte before reading it as the Escape key
+#[must_use]
+pub fn escape_timeout() -> Duration {
+    Duration::from_micros(ESCAPE_TIMEOUT_MICROS.load(Ordering::Relaxed))
+}
 
 /// Different events that can happen through the terminal
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Event {
     /// An event that happens upon a key being pressed
-    Key(Key, ButtonType, Modifiers),
+    ///
+    /// The last field says whether another key combination sends the same thing, see the
+    /// changelog for updating patterns written before it was added
+    Key(Key, ButtonType, Modifiers, Decoding),
+    /// Text that was typed, separately from the key that typed it
+    ///
+    /// Only sent when the terminal reports text on its own, that is kitty comprehensive keyboard
+    /// handling (where it follows the key event) and Windows input methods (where there is no key
+    /// event), otherwise [`Key::Char`] is the typed text
+    ///
+    /// Text fields should insert this while shortcuts match on the key, the two differ with
+    /// shift (`a` and `A`), keyboard layouts, dead keys and compose sequences
+    Text(String),
     /// An event that happens upon a mouse action
     ///
     /// The last two are the x and y position of the event, 0-based
@@ -79,6 +134,70 @@ pub enum Key {
     Char(char),
     /// The Escape key
     Escape,
+    /// The Enter/Return key
+    Enter,
+}
+
+/// Whether the terminal could have sent a key event for more than one key combination
+///
+/// Legacy *nix terminals send some keys as control characters, so for example Tab and Ctrl+I
+/// are the exact same byte, which of the two is reported is chosen by the [`LegacyKeyPolicy`]
+///
+/// Apps should avoid binding different actions to combinations that are ambiguous with each other
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum Decoding {
+    /// Only this key combination sends what was received
+    #[default]
+    Exact,
+    /// Another key combination sends the exact same thing
+    Ambiguous,
+}
+
+/// How legacy control characters that double as named keys are decoded
+///
+/// | Bytes | [`PreferNamedKeys`] | [`PreferCtrlChars`] |
+/// |-------|---------------------|---------------------|
+/// | `09`  | Tab                 | Ctrl+I              |
+/// | `08`  | Backspace           | Ctrl+H              |
+/// | `1b`  | Escape              | Ctrl+[              |
+/// | `00`  | Ctrl+Space          | Ctrl+@              |
+///
+/// `0d` is always decoded as [`Key::Enter`] like on Windows, even though Ctrl+M sends it too
+///
+/// All of these, along with Enter, Ctrl+J (`0a`, also sent for Ctrl+Enter by some terminals) and
+/// Ctrl+\ to Ctrl+_ (`1c`-`1f`, also sent for Ctrl+4 to Ctrl+7), are marked as
+/// [`Decoding::Ambiguous`], as are uppercase letters as Shift is only inferred from the case and
+/// Caps Lock sends them too
+///
+/// Terminals with kitty comprehensive keyboard handling enabled and Windows are not affected
+///
+/// [`PreferNamedKeys`]: LegacyKeyPolicy::PreferNamedKeys
+/// [`PreferCtrlChars`]: LegacyKeyPolicy::PreferCtrlChars
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum LegacyKeyPolicy {
+    /// Decode as the named key (Tab, Enter, Backspace, Escape)
+    #[default]
+    PreferNamedKeys,
+    /// Decode as Ctrl with a character
+    PreferCtrlChars,
+}
+
+static PREFER_CTRL_CHARS: AtomicBool = AtomicBool::new(false);
+
+/// Sets how legacy control characters that double as named keys are decoded
+pub fn set_legacy_key_policy(policy: LegacyKeyPolicy) {
+    let prefer_ctrl = policy == LegacyKeyPolicy::PreferCtrlChars;
+    PREFER_CTRL_CHARS.store(prefer_ctrl, Ordering::Relaxed);
+}
+
+/// How legacy control characters that double as named keys are decoded
+#[must_use]
+pub fn legacy_key_policy() -> LegacyKeyPolicy {
+    if PREFER_CTRL_CHARS.load(Ordering::Relaxed) {
+        LegacyKeyPolicy::PreferCtrlChars
+    } else {
+        LegacyKeyPolicy::PreferNamedKeys
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
@@ -90,7 +209,8 @@ pub struct Modifiers {
 }
 
 impl Modifiers {
-    #[must_use] pub const fn new(shift: bool, alt: bool, ctrl: bool) -> Self {
+    #[must_use]
+    pub const fn new(shift: bool, alt: bool, ctrl: bool) -> Self {
         Self { shift, alt, ctrl }
     }
 
@@ -134,6 +254,7 @@ pub enum ButtonType {
 pub(crate) const fn key_helper(mods: &str, key: Key) -> Event {
     let mut key_mods = Modifiers::NONE;
     let mut key_type = ButtonType::Press;
+    let mut decoding = Decoding::Exact;
 
     let string = mods.as_bytes();
     let mut i = 0;
@@ -147,16 +268,37 @@ pub(crate) const fn key_helper(mods: &str, key: Key) -> Event {
         if string[i] == b'*' {
             key_type = ButtonType::Held;
         }
+        if string[i] == b'?' {
+            decoding = Decoding::Ambiguous;
+        }
         i += 1;
     }
-    Event::Key(key, key_type, key_mods)
+    Event::Key(key, key_type, key_mods, decoding)
 }
 
-#[cfg(unix)]
-pub(crate) const fn simple_key(key: Key, shift: bool, alt: bool, ctrl: bool) -> Event {
-    Event::Key(key, ButtonType::Press, Modifiers::new(shift, alt, ctrl))
+/// Somewhere events can be polled from, either the terminal or a recording of it
+pub trait InputSource {
+    /// Attempts to fetch the next event, waiting at most `timeout`
+    ///
+    /// # Errors
+    /// If the timeout has expired or
+    /// there was an error getting the data
+    fn poll_event(&mut self, timeout: Duration) -> io::Result<Event>;
+}
+
+/// The terminal's own input, the same as calling [`poll_input`]
+#[derive(Debug, Copy, Clone, Default)]
+pub struct TerminalInput;
+
+impl InputSource for TerminalInput {
+    fn poll_event(&mut self, timeout: Duration) -> io::Result<Event> {
+        poll_input(timeout)
+    }
 }
 
+pub use crate::keymap::{DEFAULT_SEQUENCE_TIMEOUT, KeyChord, Keymap, KeymapOutput};
+pub use crate::record::{Recorder, Replayer};
+
 #[cfg(unix)]
 pub use crate::unix_input::poll_input;
 
@@ -171,7 +313,19 @@ fn test_key_helper() {
         Event::Key(
             Key::Char('c'),
             ButtonType::Held,
-            Modifiers::SHIFT.ctrl(true).alt(true)
+            Modifiers::SHIFT.ctrl(true).alt(true),
+            Decoding::Exact
         )
-    )
+    );
+}
+
+#[test]
+fn test_escape_timeout() {
+    // No other test reads the escape timeout so changing it here can't race with them
+    set_escape_timeout(Duration::from_millis(300));
+    assert_eq!(escape_timeout(), Duration::from_millis(300));
+    set_escape_timeout(Duration::MAX);
+    assert_eq!(escape_timeout(), Duration::from_micros(u64::MAX));
+    set_escape_timeout(DEFAULT_ESCAPE_TIMEOUT);
+    assert_eq!(escape_timeout(), DEFAULT_ESCAPE_TIMEOUT);
 }
@@ -0,0 +1,494 @@
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::io;
+use std::time::{Duration, Instant};
+
+use crate::input::{ButtonType, Event, InputSource, Key, Modifiers, TerminalInput};
+
+/// The default time to wait for the next key of an ambiguous sequence, same as vim's `timeoutlen`
+pub const DEFAULT_SEQUENCE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A single key combined with the modifiers held while pressing it
+///
+/// Shift is folded into ascii letters as it is already implied by the case, so `G`, `Shift+G`
+/// and `Shift+g` (as reported by kitty comprehensive keyboard handling) are the same chord
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    pub key: Key,
+    pub modifiers: Modifiers,
+}
+
+impl KeyChord {
+    #[must_use]
+    pub const fn new(key: Key, modifiers: Modifiers) -> Self {
+        match key {
+            Key::Char(c) if c.is_ascii_uppercase() => Self {
+                key,
+                modifiers: modifiers.shift(false),
+            },
+            Key::Char(c) if c.is_ascii_lowercase() && modifiers.shift => Self {
+                key: Key::Char(c.to_ascii_uppercase()),
+                modifiers: modifiers.shift(false),
+            },
+            _ => Self { key, modifiers },
+        }
+    }
+
+    /// A chord of a character with no modifiers
+    #[must_use]
+    pub const fn char(c: char) -> Self {
+        Self::new(Key::Char(c), Modifiers::NONE)
+    }
+
+    /// A chord of a character with control held
+    #[must_use]
+    pub const fn ctrl(c: char) -> Self {
+        Self::new(Key::Char(c), Modifiers::CTRL)
+    }
+
+    /// A chord of a character with alt held
+    #[must_use]
+    pub const fn alt(c: char) -> Self {
+        Self::new(Key::Char(c), Modifiers::ALT)
+    }
+
+    /// Gets the chord of a key event, if it is a press or a repeat
+    #[must_use]
+    pub fn from_event(event: &Event) -> Option<Self> {
+        match event {
+            Event::Key(key, ButtonType::Press | ButtonType::Held, modifiers, _) => {
+                Some(Self::new(*key, *modifiers))
+            }
+            _ => None,
+        }
+    }
+
+    fn digit(self) -> Option<u32> {
+        match self.key {
+            Key::Char(c) if self.modifiers == Modifiers::NONE => c.to_digit(10),
+            _ => None,
+        }
+    }
+}
+
+impl From<Key> for KeyChord {
+    fn from(key: Key) -> Self {
+        Self::new(key, Modifiers::NONE)
+    }
+}
+
+/// The result of feeding input to a [`Keymap`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeymapOutput<A> {
+    /// A bound sequence was typed, with the count typed before it if there was one
+    Action(A, Option<u32>),
+    /// An event that is not part of any bound sequence, in insert-like modes this is typed text
+    Unmatched(Event),
+}
+
+struct Node<A> {
+    action: Option<A>,
+    children: HashMap<KeyChord, Node<A>>,
+}
+
+impl<A> Node<A> {
+    fn new() -> Self {
+        Self {
+            action: None,
+            children: HashMap::new(),
+        }
+    }
+}
+
+/// A trie of key sequences per mode, matching events from [`poll_input`] into actions
+///
+/// [`poll_input`]: crate::input::poll_input
+///
+/// Sequences can be single chords (`Ctrl+S`), multi-key (`g g`, `Ctrl+X Ctrl+S`) and, in modes
+/// with counts enabled, be prefixed by a count (`3 d d`)
+///
+/// When a typed sequence is both bound and the prefix of a longer binding the keymap waits for
+/// [`Keymap::set_timeout`] before settling on the shorter one, much like vim's `timeoutlen`
+///
+/// The [`Event::Text`] a key typed stays with that key, it is dropped when the key is part of an
+/// action and passed on right after the key otherwise
+///
+/// ```no_run
+/// use neutuino::input::{KeyChord, Keymap, KeymapOutput};
+/// use std::time::Duration;
+///
+/// #[derive(Clone, Copy, PartialEq, Eq, Hash)]
+/// enum Mode { Normal, Insert }
+///
+/// let mut keymap = Keymap::new(Mode::Normal);
+/// keymap.set_counts(Mode::Normal, true);
+/// keymap.bind(Mode::Normal, &[KeyChord::char('g'), KeyChord::char('g')], "top");
+/// keymap.bind(Mode::Normal, &[KeyChord::ctrl('x'), KeyChord::ctrl('s')], "save");
+/// keymap.bind(Mode::Insert, &[KeyChord::char('j'), KeyChord::char('k')], "normal");
+///
+/// loop {
+///     let Ok(outputs) = keymap.poll(Duration::from_millis(100)) else { continue };
+///     for output in outputs {
+///         match output {
+///             KeymapOutput::Action(action, count) => println!("{action} x{count:?}\r"),
+///             KeymapOutput::Unmatched(event) => println!("{event:?}\r"),
+///         }
+///     }
+/// }
+/// ```
+pub struct Keymap<M, A> {
+    modes: HashMap<M, Node<A>>,
+    counts: HashSet<M>,
+    mode: M,
+    timeout: Duration,
+    pending: Vec<Event>,
+    count: Option<u32>,
+    count_len: usize,
+    deadline: Option<Instant>,
+    /// Whether the last key was part of an action, so the text it typed is dropped
+    drop_text: bool,
+}
+
+impl<M, A> Keymap<M, A>
+where
+    M: Copy + Eq + Hash,
+    A: Clone,
+{
+    /// Creates an empty keymap starting in `mode`
+    #[must_use]
+    pub fn new(mode: M) -> Self {
+        Self {
+            modes: HashMap::new(),
+            counts: HashSet::new(),
+            mode,
+            timeout: DEFAULT_SEQUENCE_TIMEOUT,
+            pending: Vec::new(),
+            count: None,
+            count_len: 0,
+            deadline: None,
+            drop_text: false,
+        }
+    }
+
+    /// Binds a sequence of chords to an action in a mode
+    ///
+    /// Returns the action previously bound to the exact same sequence, binding an empty
+    /// sequence does nothing
+    pub fn bind(&mut self, mode: M, sequence: &[KeyChord], action: A) -> Option<A> {
+        if sequence.is_empty() {
+            return None;
+        }
+        let mut node = self.modes.entry(mode).or_insert_with(Node::new);
+        for chord in sequence {
+            node = node.children.entry(*chord).or_insert_with(Node::new);
+        }
+        node.action.replace(action)
+    }
+
+    /// Removes the binding of a sequence in a mode, returning its action
+    ///
+    /// Longer bindings that start with this sequence are kept
+    pub fn unbind(&mut self, mode: M, sequence: &[KeyChord]) -> Option<A> {
+        let mut node = self.modes.get_mut(&mode)?;
+        for chord in sequence {
+            node = node.children.get_mut(chord)?;
+        }
+        node.action.take()
+    }
+
+    /// Enables or disables count prefixes (`3 d d`) in a mode, they are disabled by default
+    ///
+    /// While enabled, plain digits that start a sequence are taken as a count instead of matched
+    /// against bindings, except for a leading `0`
+    pub fn set_counts(&mut self, mode: M, enabled: bool) {
+        if enabled {
+            self.counts.insert(mode);
+        } else {
+            self.counts.remove(&mode);
+        }
+    }
+
+    /// Sets how long to wait for the next key of an unfinished sequence
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// The current mode
+    #[must_use]
+    pub fn mode(&self) -> M {
+        self.mode
+    }
+
+    /// Switches to another mode, dropping any partially typed sequence
+    pub fn set_mode(&mut self, mode: M) {
+        self.mode = mode;
+        self.reset();
+    }
+
+    /// Whether a partially typed sequence or count is waiting for more input
+    #[must_use]
+    pub fn is_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// How long until the partially typed sequence times out, if there is one
+    #[must_use]
+    pub fn timeout_remaining(&self) -> Option<Duration> {
+        self.deadline
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    /// Feeds an event to the keymap, returning everything it resolved to
+    ///
+    /// An empty result means the event started or continued a sequence, call [`Keymap::tick`]
+    /// once [`Keymap::timeout_remaining`] runs out to settle it
+    pub fn feed(&mut self, event: Event) -> Vec<KeymapOutput<A>> {
+        let mut output = Vec::new();
+        self.feed_at(event, Instant::now(), &mut output);
+        output
+    }
+
+    /// Settles a partially typed sequence if its timeout has run out
+    pub fn tick(&mut self) -> Vec<KeymapOutput<A>> {
+        let mut output = Vec::new();
+        self.tick_at(Instant::now(), &mut output);
+        output
+    }
+
+    /// Polls for input with [`poll_input`] and feeds it to the keymap
+    ///
+    /// [`poll_input`]: crate::input::poll_input
+    ///
+    /// Waits at most `timeout`, or less if a pending sequence times out first
+    ///
+    /// # Errors
+    /// If the timeout has expired without anything being resolved or
+    /// there was an error getting the data
+    pub fn poll(&mut self, timeout: Duration) -> io::Result<Vec<KeymapOutput<A>>> {
+        self.poll_from(&mut TerminalInput, timeout)
+    }
+
+    /// Same as [`Keymap::poll`] but reads from any input source, such as a [`Replayer`]
+    ///
+    /// [`Replayer`]: crate::input::Replayer
+    ///
+    /// # Errors
+    /// If the timeout has expired without anything being resolved or
+    /// there was an error getting the data
+    pub fn poll_from(
+        &mut self,
+        source: &mut impl InputSource,
+        timeout: Duration,
+    ) -> io::Result<Vec<KeymapOutput<A>>> {
+        let output = self.tick();
+        if !output.is_empty() {
+            return Ok(output);
+        }
+        let wait = self
+            .timeout_remaining()
+            .map_or(timeout, |remaining| remaining.min(timeout));
+        match source.poll_event(wait) {
+            Ok(event) => Ok(self.feed(event)),
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => {
+                let output = self.tick();
+                if output.is_empty() {
+                    Err(e)
+                } else {
+                    Ok(output)
+                }
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    fn reset(&mut self) {
+        self.pending.clear();
+        self.count = None;
+        self.count_len = 0;
+        self.deadline = None;
+    }
+
+    fn tick_at(&mut self, now: Instant, output: &mut Vec<KeymapOutput<A>>) {
+        if self.deadline.is_some_and(|deadline| deadline <= now) {
+            self.settle(now, output);
+        }
+    }
+
+    fn feed_at(&mut self, event: Event, now: Instant, output: &mut Vec<KeymapOutput<A>>) {
+        if matches!(event, Event::Text(_)) {
+            if !self.pending.is_empty() {
+                self.pending.push(event);
+            } else if !self.drop_text {
+                output.push(KeymapOutput::Unmatched(event));
+            }
+            return;
+        }
+        self.drop_text = false;
+        let Some(chord) = KeyChord::from_event(&event) else {
+            output.push(KeymapOutput::Unmatched(event));
+            return;
+        };
+
+        if self.sequence().next().is_none()
+            && self.counts.contains(&self.mode)
+            && let Some(digit) = chord.digit().filter(|&d| d != 0 || self.count.is_some())
+        {
+            let count = self.count.unwrap_or(0);
+            self.count = Some(count.saturating_mul(10).saturating_add(digit));
+            self.count_len += 1;
+            self.pending.push(event);
+            self.deadline = None;
+            return;
+        }
+
+        let sequence = self.sequence().chain([chord]);
+        let (action, is_prefix) = self.lookup(sequence).map_or((None, false), |node| {
+            (node.action.clone(), !node.children.is_empty())
+        });
+        match (action, is_prefix) {
+            (_, true) => {
+                self.pending.push(event);
+                self.deadline = Some(now + self.timeout);
+            }
+            (Some(action), false) => {
+                output.push(KeymapOutput::Action(action, self.count));
+                self.reset();
+                self.drop_text = true;
+            }
+            (None, false) if self.pending.is_empty() => {
+                output.push(KeymapOutput::Unmatched(event));
+            }
+            (None, false) => {
+                self.pending.push(event);
+                self.settle(now, output);
+            }
+        }
+    }
+
+    /// Resolves the pending events into the longest bound prefix, or if there is none, gives up
+    /// on the first event, then refeeds whatever is left over
+    fn settle(&mut self, now: Instant, output: &mut Vec<KeymapOutput<A>>) {
+        let chords: Vec<KeyChord> = self.sequence().collect();
+        let matched = (1..=chords.len()).rev().find_map(|len| {
+            let action = self.lookup(chords[..len].iter().copied())?.action.clone()?;
+            Some((len, action))
+        });
+
+        let mut pending = std::mem::take(&mut self.pending);
+        let count = self.count;
+        let count_len = self.count_len;
+        self.reset();
+
+        let leftover = if let Some((len, action)) = matched {
+            output.push(KeymapOutput::Action(action, count));
+            split_after_keys(&mut pending, count_len + len)
+        } else {
+            let leftover = split_after_keys(&mut pending, count_len + 1);
+            output.extend(pending.into_iter().map(KeymapOutput::Unmatched));
+            leftover
+        };
+        for event in leftover {
+            self.feed_at(event, now, output);
+        }
+    }
+
+    fn sequence(&self) -> impl Iterator<Item = KeyChord> + '_ {
+        self.pending
+            .iter()
+            .filter_map(KeyChord::from_event)
+            .skip(self.count_len)
+    }
+
+    fn lookup(&self, sequence: impl IntoIterator<Item = KeyChord>) -> Option<&Node<A>> {
+        let mut node = self.modes.get(&self.mode)?;
+        for chord in sequence {
+            node = node.children.get(&chord)?;
+        }
+        Some(node)
+    }
+}
+
+/// Splits the pending events after `keys` keys and the text they typed, returning the rest
+fn split_after_keys(pending: &mut Vec<Event>, keys: usize) -> Vec<Event> {
+    let end = pending
+        .iter()
+        .enumerate()
+        .filter(|(_, event)| !matches!(event, Event::Text(_)))
+        .nth(keys)
+        .map_or(pending.len(), |(i, _)| i);
+    pending.split_off(end)
+}
+
+#[test]
+fn test_keymap_sequences() {
+    let press = |c| crate::input::key_helper("", Key::Char(c));
+    let mut keymap = Keymap::new(0);
+    keymap.set_counts(0, true);
+    keymap.bind(0, &[KeyChord::char('g')], "g");
+    keymap.bind(0, &[KeyChord::char('g'), KeyChord::char('g')], "gg");
+    keymap.bind(0, &[KeyChord::char('d'), KeyChord::char('d')], "dd");
+
+    let now = Instant::now();
+    let mut output = Vec::new();
+    for c in "12dd".chars() {
+        keymap.feed_at(press(c), now, &mut output);
+    }
+    assert_eq!(output, [KeymapOutput::Action("dd", Some(12))]);
+
+    output.clear();
+    keymap.feed_at(press('g'), now, &mut output);
+    keymap.tick_at(now, &mut output);
+    assert!(output.is_empty() && keymap.is_pending());
+    keymap.tick_at(now + DEFAULT_SEQUENCE_TIMEOUT, &mut output);
+    assert_eq!(output, [KeymapOutput::Action("g", None)]);
+
+    output.clear();
+    for c in "dgg0x".chars() {
+        keymap.feed_at(press(c), now, &mut output);
+    }
+    assert_eq!(
+        output,
+        [
+            KeymapOutput::Unmatched(press('d')),
+            KeymapOutput::Action("gg", None),
+            KeymapOutput::Unmatched(press('0')),
+            KeymapOutput::Unmatched(press('x')),
+        ]
+    );
+
+    // The text a key typed follows it, or is dropped with it when it is part of an action
+    let text = |c: char| Event::Text(c.to_string());
+    let mut keymap = Keymap::new(0);
+    keymap.bind(0, &[KeyChord::char('j'), KeyChord::char('k')], "jk");
+    let mut output = Vec::new();
+    for event in [press('j'), text('j'), press('k'), text('k')] {
+        keymap.feed_at(event, now, &mut output);
+    }
+    assert_eq!(output, [KeymapOutput::Action("jk", None)]);
+
+    output.clear();
+    for event in [press('j'), text('j'), press('x'), text('x')] {
+        keymap.feed_at(event, now, &mut output);
+    }
+    assert_eq!(
+        output,
+        [
+            KeymapOutput::Unmatched(press('j')),
+            KeymapOutput::Unmatched(text('j')),
+            KeymapOutput::Unmatched(press('x')),
+            KeymapOutput::Unmatched(text('x')),
+        ]
+    );
+
+    output.clear();
+    keymap.feed_at(press('j'), now, &mut output);
+    keymap.feed_at(text('j'), now, &mut output);
+    keymap.tick_at(now + DEFAULT_SEQUENCE_TIMEOUT, &mut output);
+    assert_eq!(
+        output,
+        [
+            KeymapOutput::Unmatched(press('j')),
+            KeymapOutput::Unmatched(text('j')),
+        ]
+    );
+}
@@ -14,6 +14,29 @@ mod unix_input;
 #[cfg(windows)]
 mod windows_input;
 
+mod ansi_text;
+mod color;
+mod command;
+mod cursor;
+mod grapheme;
+mod grapheme_tables;
+mod hyperlink;
+mod keymap;
+mod notification;
+mod palette;
+mod policy;
+mod progress;
+mod query;
+mod record;
+mod shell;
+mod style;
+mod synchronized;
+mod text;
+mod underline;
+mod width;
+mod width_tables;
+mod window;
+
 pub mod ansi;
 pub mod control;
 pub mod input;
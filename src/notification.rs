@@ -0,0 +1,219 @@
+use std::env;
+use std::fmt;
+use std::sync::atomic::{AtomicU8, AtomicU32, Ordering};
+
+use crate::command::{Command, impl_display};
+
+/// The escape sequence used to send desktop notifications
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum NotificationProtocol {
+    /// `OSC 9`, from iTerm2 and supported by most terminals, only has a message so the title and
+    /// body are joined
+    Iterm2,
+    /// `OSC 777;notify`, from rxvt and supported by VTE based terminals
+    Rxvt,
+    /// `OSC 99`, from kitty
+    Kitty,
+}
+
+/// 0 until the protocol has been detected or set, then the protocol + 1
+static NOTIFICATION_PROTOCOL: AtomicU8 = AtomicU8::new(0);
+
+/// Sets the escape sequence used to send desktop notifications
+pub fn set_notification_protocol(protocol: NotificationProtocol) {
+    NOTIFICATION_PROTOCOL.store(protocol as u8 + 1, Ordering::Relaxed);
+}
+
+/// The escape sequence used to send desktop notifications
+///
+/// Detected from the environment with [`detect_notification_protocol`] unless set with
+/// [`set_notification_protocol`]
+#[must_use]
+pub fn notification_protocol() -> NotificationProtocol {
+    match NOTIFICATION_PROTOCOL.load(Ordering::Relaxed) {
+        1 => NotificationProtocol::Iterm2,
+        2 => NotificationProtocol::Rxvt,
+        3 => NotificationProtocol::Kitty,
+        _ => {
+            let protocol = detect_notification_protocol();
+            set_notification_protocol(protocol);
+            protocol
+        }
+    }
+}
+
+/// Detects the notification protocol of the terminal from the `TERM`, `KITTY_WINDOW_ID` and
+/// `VTE_VERSION` environment variables, falling back to [`NotificationProtocol::Iterm2`]
+#[must_use]
+pub fn detect_notification_protocol() -> NotificationProtocol {
+    detect_notification_protocol_from(|name| env::var(name).ok())
+}
+
+fn detect_notification_protocol_from(var: impl Fn(&str) -> Option<String>) -> NotificationProtocol {
+    let term = var("TERM").unwrap_or_default();
+    if term.contains("kitty") || var("KITTY_WINDOW_ID").is_some() {
+        NotificationProtocol::Kitty
+    } else if term.starts_with("rxvt") || term.contains("foot") || var("VTE_VERSION").is_some() {
+        NotificationProtocol::Rxvt
+    } else {
+        NotificationProtocol::Iterm2
+    }
+}
+
+/// Shows a desktop notification, usually only when the terminal isn't focused
+///
+/// Control characters are removed from the title and body so they can come from untrusted input
+///
+/// Kitty shows a notification with the same id as an earlier one in its place, [`new`](Self::new)
+/// gives each notification a fresh id, copies share it
+///
+/// ```no_run
+/// use neutuino::ansi::Notification;
+///
+/// print!("{}", Notification::new("Build finished", "all 42 jobs passed"));
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Notification<'a> {
+    pub title: &'a str,
+    pub body: &'a str,
+    /// The protocol to use instead of [`notification_protocol`]
+    pub protocol: Option<NotificationProtocol>,
+    /// Identifies the notification to kitty, ignored by the other protocols
+    pub id: u32,
+}
+
+/// The id given to the next notification
+static NEXT_ID: AtomicU32 = AtomicU32::new(1);
+
+impl<'a> Notification<'a> {
+    #[must_use]
+    pub fn new(title: &'a str, body: &'a str) -> Self {
+        Self {
+            title,
+            body,
+            protocol: None,
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+
+    /// Sets the id, sending a notification with the id of an earlier one replaces it in kitty
+    #[must_use]
+    pub const fn id(self, id: u32) -> Self {
+        Self { id, ..self }
+    }
+
+    /// Sends the notification with `protocol` regardless of the detected one
+    #[must_use]
+    pub const fn protocol(self, protocol: NotificationProtocol) -> Self {
+        Self {
+            protocol: Some(protocol),
+            ..self
+        }
+    }
+}
+
+impl Command for Notification<'_> {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        match self.protocol.unwrap_or_else(notification_protocol) {
+            NotificationProtocol::Iterm2 => {
+                f.write_str("\x1b]9;")?;
+                if self.title.is_empty() {
+                    write_message_start(f, self.body)?;
+                } else {
+                    write_message_start(f, self.title)?;
+                    if !self.body.is_empty() {
+                        f.write_str(": ")?;
+                    }
+                    write_escaped(f, self.body, false)?;
+                }
+            }
+            NotificationProtocol::Rxvt => {
+                // The title is a field of its own so it can't contain the separator
+                f.write_str("\x1b]777;notify;")?;
+                write_escaped(f, self.title, true)?;
+                f.write_char(';')?;
+                write_escaped(f, self.body, false)?;
+            }
+            NotificationProtocol::Kitty => {
+                // The title and body are sent as two parts of one notification
+                write!(f, "\x1b]99;i={}:d=0:p=title;", self.id)?;
+                write_escaped(f, self.title, false)?;
+                write!(f, "\x1b\\\x1b]99;i={}:d=1:p=body;", self.id)?;
+                write_escaped(f, self.body, false)?;
+            }
+        }
+        f.write_str("\x1b\\")
+    }
+}
+
+impl_display!(Notification<'_>);
+
+/// Writes text without control characters, which could end the escape sequence early, replacing
+/// semicolons with commas if `separator` is true
+fn write_escaped(f: &mut impl fmt::Write, text: &str, separator: bool) -> fmt::Result {
+    for c in text.chars().filter(|c| !c.is_control()) {
+        f.write_char(if separator && c == ';' { ',' } else { c })?;
+    }
+    Ok(())
+}
+
+/// Writes the start of an `OSC 9` message like [`write_escaped`], a number followed by a semicolon
+/// is a ConEmu command such as `4;` for progress so the semicolon is replaced with a comma
+fn write_message_start(f: &mut impl fmt::Write, text: &str) -> fmt::Result {
+    let mut text = text.chars().filter(|c| !c.is_control()).peekable();
+    let mut digits = false;
+    while let Some(c) = text.next_if(char::is_ascii_digit) {
+        f.write_char(c)?;
+        digits = true;
+    }
+    if digits && text.next_if_eq(&';').is_some() {
+        f.write_char(',')?;
+    }
+    text.try_for_each(|c| f.write_char(c))
+}
+
+#[test]
+fn test_notification() {
+    let notification = Notification::new("Done;ok", "3\x1b]; jobs\x07").id(7);
+    assert_eq!(
+        notification
+            .protocol(NotificationProtocol::Iterm2)
+            .to_string(),
+        "\x1b]9;Done;ok: 3]; jobs\x1b\\"
+    );
+    assert_eq!(
+        notification
+            .protocol(NotificationProtocol::Rxvt)
+            .to_string(),
+        "\x1b]777;notify;Done,ok;3]; jobs\x1b\\"
+    );
+    assert_eq!(
+        notification
+            .protocol(NotificationProtocol::Kitty)
+            .to_string(),
+        "\x1b]99;i=7:d=0:p=title;Done;ok\x1b\\\x1b]99;i=7:d=1:p=body;3]; jobs\x1b\\"
+    );
+    assert_ne!(Notification::new("a", "").id, Notification::new("a", "").id);
+    let progress = |title, body| {
+        Notification::new(title, body)
+            .protocol(NotificationProtocol::Iterm2)
+            .to_string()
+    };
+    assert_eq!(progress("", "4;1;50"), "\x1b]9;4,1;50\x1b\\");
+    assert_eq!(progress("4\x07;3", "x;y"), "\x1b]9;4,3: x;y\x1b\\");
+    assert_eq!(progress("42", "4;"), "\x1b]9;42: 4;\x1b\\");
+
+    let detect = |vars| detect_notification_protocol_from(crate::policy::env_from(vars));
+    assert_eq!(
+        detect(&[("TERM", "xterm-kitty")]),
+        NotificationProtocol::Kitty
+    );
+    assert_eq!(
+        detect(&[("TERM", "xterm-256color"), ("VTE_VERSION", "7600")]),
+        NotificationProtocol::Rxvt
+    );
+    assert_eq!(
+        detect(&[("TERM", "xterm-256color")]),
+        NotificationProtocol::Iterm2
+    );
+}
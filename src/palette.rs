@@ -0,0 +1,241 @@
+use std::fmt::{self, Write as _};
+use std::io::{self, Write};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use crate::color::{Color, parse_color_spec};
+use crate::command::{Command, impl_display};
+use crate::query::{query, string_reply};
+
+/// Changes a palette entry, e.g. `1` changes what [`Color::Red`] looks like
+///
+/// Palette colors are sent as xterm's default values, [`Color::Default`] resets the entry
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SetPaletteColor(pub u8, pub Color);
+
+/// Resets a palette entry to the user-specified default
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ResetPaletteColor(pub u8);
+
+/// Resets every palette entry to the user-specified default
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ResetPalette;
+
+/// Changes the color text uses when no foreground is set, [`Color::Default`] resets it
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SetDefaultForeground(pub Color);
+
+/// Changes the color the background uses when no background is set, [`Color::Default`] resets
+/// it
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SetDefaultBackground(pub Color);
+
+/// Resets the default foreground color to the user-specified default
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ResetDefaultForeground;
+
+/// Resets the default background color to the user-specified default
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ResetDefaultBackground;
+
+/// Writes an OSC setting a color as `rgb:rr/gg/bb`
+fn write_color(f: &mut impl fmt::Write, osc: impl fmt::Display, color: Color) -> fmt::Result {
+    let (red, green, blue) = color.rgb();
+    write!(f, "\x1b]{osc};rgb:{red:02x}/{green:02x}/{blue:02x}\x1b\\")
+}
+
+impl Command for SetPaletteColor {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        match self.1 {
+            Color::Default => ResetPaletteColor(self.0).write_ansi(f),
+            color => write_color(f, format_args!("4;{}", self.0), color),
+        }
+    }
+}
+
+impl Command for ResetPaletteColor {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        write!(f, "\x1b]104;{}\x1b\\", self.0)
+    }
+}
+
+impl Command for ResetPalette {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        f.write_str("\x1b]104\x1b\\")
+    }
+}
+
+impl Command for SetDefaultForeground {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        match self.0 {
+            Color::Default => ResetDefaultForeground.write_ansi(f),
+            color => write_color(f, "10", color),
+        }
+    }
+}
+
+impl Command for SetDefaultBackground {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        match self.0 {
+            Color::Default => ResetDefaultBackground.write_ansi(f),
+            color => write_color(f, "11", color),
+        }
+    }
+}
+
+impl Command for ResetDefaultForeground {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        f.write_str("\x1b]110\x1b\\")
+    }
+}
+
+impl Command for ResetDefaultBackground {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        f.write_str("\x1b]111\x1b\\")
+    }
+}
+
+/// The 16 named colors and the default foreground and background colors of the terminal
+///
+/// Anything the terminal didn't report is [`Color::Default`], writing it as a command restores
+/// it, resetting the colors that weren't reported
+///
+/// The cursor color belongs to [`CursorState`](crate::control::CursorState)
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Palette {
+    pub colors: [Color; 16],
+    pub foreground: Color,
+    pub background: Color,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self {
+            colors: [Color::Default; 16],
+            foreground: Color::Default,
+            background: Color::Default,
+        }
+    }
+}
+
+impl Palette {
+    /// Asks the terminal for its palette
+    ///
+    /// This needs raw mode to be enabled
+    ///
+    /// # Errors
+    /// If the terminal can't be written to or read from or
+    /// it didn't reply before the timeout
+    pub fn query(timeout: Duration) -> io::Result<Self> {
+        let mut request = String::new();
+        for i in 0..16 {
+            // Writing to a string can't fail
+            let _ = write!(request, "\x1b]4;{i};?\x1b\\");
+        }
+        request.push_str("\x1b]10;?\x1b\\\x1b]11;?\x1b\\");
+        let reply = query(&request, timeout)?;
+        Ok(Self::from_reply(&reply))
+    }
+
+    fn from_reply(reply: &[u8]) -> Self {
+        let color = |introducer: &str| {
+            string_reply(reply, introducer)
+                .and_then(parse_color_spec)
+                .unwrap_or(Color::Default)
+        };
+        Self {
+            colors: std::array::from_fn(|i| color(&format!("\x1b]4;{i};"))),
+            foreground: color("\x1b]10;"),
+            background: color("\x1b]11;"),
+        }
+    }
+}
+
+impl Command for Palette {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        for (i, &color) in (0..).zip(&self.colors) {
+            SetPaletteColor(i, color).write_ansi(f)?;
+        }
+        SetDefaultForeground(self.foreground).write_ansi(f)?;
+        SetDefaultBackground(self.background).write_ansi(f)
+    }
+}
+
+impl_display!(
+    SetPaletteColor,
+    ResetPaletteColor,
+    ResetPalette,
+    SetDefaultForeground,
+    SetDefaultBackground,
+    ResetDefaultForeground,
+    ResetDefaultBackground,
+    Palette
+);
+
+static SAVED_PALETTE: Mutex<Option<Palette>> = Mutex::new(None);
+
+/// Saves the palette to be restored by [`restore_palette`], call this before changing it
+///
+/// If the terminal doesn't reply the default palette is saved
+///
+/// # Errors
+/// If the terminal can't be written to or read from
+pub fn save_palette(timeout: Duration) -> io::Result<()> {
+    let palette = match Palette::query(timeout) {
+        Ok(palette) => palette,
+        Err(e) if e.kind() == io::ErrorKind::TimedOut => Palette::default(),
+        Err(e) => return Err(e),
+    };
+    *SAVED_PALETTE.lock().unwrap_or_else(PoisonError::into_inner) = Some(palette);
+    Ok(())
+}
+
+/// Restores the palette saved by [`save_palette`], does nothing if none was saved
+///
+/// Done by [`tui_deinit`](crate::control::tui_deinit) and
+/// [`cli_deinit`](crate::control::cli_deinit)
+///
+/// # Errors
+/// If stdout can't be written to
+pub fn restore_palette() -> io::Result<()> {
+    let Some(palette) = SAVED_PALETTE
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .take()
+    else {
+        return Ok(());
+    };
+    let mut stdout = io::stdout().lock();
+    palette.write_to(&mut stdout)?;
+    stdout.flush()
+}
+
+#[test]
+fn test_palette() {
+    let reply =
+        b"\x1b]4;1;rgb:cdcd/0000/0000\x1b\\\x1b]4;10;rgb:00/ff/00\x07\x1b]11;rgb:0/0/0\x1b\\";
+    let palette = Palette::from_reply(reply);
+    assert_eq!(palette.colors[1], Color::Rgb(205, 0, 0));
+    assert_eq!(palette.colors[10], Color::Rgb(0, 255, 0));
+    assert_eq!(palette.colors[0], Color::Default);
+    assert_eq!(palette.background, Color::Rgb(0, 0, 0));
+    assert_eq!(palette.foreground, Color::Default);
+
+    assert_eq!(
+        SetPaletteColor(4, Color::Rgb(0, 0, 128)).to_string(),
+        "\x1b]4;4;rgb:00/00/80\x1b\\"
+    );
+    assert_eq!(
+        SetPaletteColor(4, Color::Default).to_string(),
+        "\x1b]104;4\x1b\\"
+    );
+    assert_eq!(
+        SetDefaultBackground(Color::Rgb(255, 255, 255)).to_string(),
+        "\x1b]11;rgb:ff/ff/ff\x1b\\"
+    );
+    assert!(
+        Palette::default()
+            .to_string()
+            .ends_with("\x1b]104;15\x1b\\\x1b]110\x1b\\\x1b]111\x1b\\")
+    );
+}
@@ -0,0 +1,339 @@
+use std::cell::Cell;
+use std::env;
+use std::fmt;
+use std::io::{self, IsTerminal, Write};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use crate::command::write_fmt;
+
+/// Whether colors and styles are emitted
+///
+/// Only typed commands follow the policy, the string constants in [`ansi`](crate::ansi) are
+/// always emitted, wrap the output in a [`ColorWriter`] to strip them
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum ColorPolicy {
+    /// Emit colors if the output is a terminal, honouring `NO_COLOR`, `FORCE_COLOR`,
+    /// `CLICOLOR_FORCE` and `TERM=dumb`
+    #[default]
+    Auto,
+    /// Always emit colors
+    Always,
+    /// Never emit colors
+    Never,
+}
+
+impl ColorPolicy {
+    /// Whether colors should be emitted to `stream` under this policy
+    ///
+    /// The environment is only read the first time, whether `stream` is a terminal every time
+    #[must_use]
+    pub fn enabled_for(self, stream: &impl IsTerminal) -> bool {
+        match self {
+            Self::Auto => auto_env().unwrap_or_else(|| stream.is_terminal()),
+            Self::Always => true,
+            Self::Never => false,
+        }
+    }
+}
+
+/// 0 until the environment has been read, then 1 if it disables colors, 2 if it forces them and
+/// 3 if it leaves it to whether the output is a terminal
+static AUTO_ENV: AtomicU8 = AtomicU8::new(0);
+
+/// Whether the environment forces colors on or off, `None` if it depends on the output
+fn auto_env() -> Option<bool> {
+    match AUTO_ENV.load(Ordering::Relaxed) {
+        1 => Some(false),
+        2 => Some(true),
+        3 => None,
+        _ => {
+            let enabled = auto_env_from(|name| env::var(name).ok());
+            AUTO_ENV.store(
+                enabled.map_or(3, |enabled| u8::from(enabled) + 1),
+                Ordering::Relaxed,
+            );
+            enabled
+        }
+    }
+}
+
+fn auto_env_from(var: impl Fn(&str) -> Option<String>) -> Option<bool> {
+    let set = |name| var(name).is_some_and(|value| !value.is_empty());
+    let forced =
+        |name| var(name).is_some_and(|value| !matches!(value.as_str(), "" | "0" | "false"));
+    if forced("FORCE_COLOR") || forced("CLICOLOR_FORCE") {
+        return Some(true);
+    }
+    if set("NO_COLOR") || var("TERM").is_some_and(|term| term == "dumb") {
+        return Some(false);
+    }
+    None
+}
+
+static COLOR_POLICY: AtomicU8 = AtomicU8::new(ColorPolicy::Auto as u8);
+/// 0 until the policy has been resolved for stdout, then 1 for disabled and 2 for enabled
+static COLORS_ENABLED: AtomicU8 = AtomicU8::new(0);
+
+thread_local! {
+    /// The colors of the [`ColorWriter`] a command is being queued to, which take precedence over
+    /// stdout's
+    static TARGET_COLORS: Cell<Option<bool>> = const { Cell::new(None) };
+}
+
+/// Sets whether the color and style commands emit anything
+pub fn set_color_policy(policy: ColorPolicy) {
+    COLOR_POLICY.store(policy as u8, Ordering::Relaxed);
+    COLORS_ENABLED.store(0, Ordering::Relaxed);
+}
+
+/// The policy deciding whether the color and style commands emit anything
+#[must_use]
+pub fn color_policy() -> ColorPolicy {
+    match COLOR_POLICY.load(Ordering::Relaxed) {
+        1 => ColorPolicy::Always,
+        2 => ColorPolicy::Never,
+        _ => ColorPolicy::Auto,
+    }
+}
+
+/// Whether the color and style commands emit anything
+///
+/// This is [`color_policy`] resolved for stdout, except while a command is written to a
+/// [`ColorWriter`] where it is that writer's colors
+#[must_use]
+pub fn colors_enabled() -> bool {
+    if let Some(enabled) = TARGET_COLORS.get() {
+        return enabled;
+    }
+    match COLORS_ENABLED.load(Ordering::Relaxed) {
+        1 => false,
+        2 => true,
+        _ => {
+            let enabled = colors_enabled_for(&io::stdout());
+            COLORS_ENABLED.store(if enabled { 2 } else { 1 }, Ordering::Relaxed);
+            enabled
+        }
+    }
+}
+
+/// Whether colors should be emitted to `stream`, [`color_policy`] resolved for it
+#[must_use]
+pub fn colors_enabled_for(stream: &impl IsTerminal) -> bool {
+    color_policy().enabled_for(stream)
+}
+
+/// Runs `f` with the color and style commands emitting colors if `enabled`, on this thread only
+pub(crate) fn with_colors<R>(enabled: bool, f: impl FnOnce() -> R) -> R {
+    let previous = TARGET_COLORS.replace(Some(enabled));
+    let result = f();
+    TARGET_COLORS.set(previous);
+    result
+}
+
+/// Runs `f` with colors enabled on this thread, truecolor, underline styles, synchronized output
+/// and hyperlinks supported
+///
+/// Tests that need these share one lock as the settings are global, and they are restored after
+#[cfg(test)]
+pub(crate) fn with_full_terminal<R>(f: impl FnOnce() -> R) -> R {
+    use crate::color::{ColorDepth, color_depth, set_color_depth};
+    use crate::hyperlink::{hyperlinks_enabled, set_hyperlinks};
+    use crate::synchronized::{set_synchronized_output, synchronized_output};
+    use crate::underline::{set_styled_underlines, styled_underlines};
+    use std::sync::{Mutex, PoisonError};
+
+    struct Restore(ColorDepth, bool, bool, bool);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            set_color_depth(self.0);
+            set_styled_underlines(self.1);
+            set_synchronized_output(self.2);
+            set_hyperlinks(self.3);
+        }
+    }
+
+    static LOCK: Mutex<()> = Mutex::new(());
+    let _lock = LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+    let _restore = Restore(
+        color_depth(),
+        styled_underlines(),
+        synchronized_output(),
+        hyperlinks_enabled(),
+    );
+    set_color_depth(ColorDepth::TrueColor);
+    set_styled_underlines(true);
+    set_synchronized_output(true);
+    set_hyperlinks(true);
+    with_colors(true, f)
+}
+
+/// An environment with only `vars` set, for the `*_from` detection functions
+#[cfg(test)]
+pub(crate) fn env_from(vars: &'static [(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+    move |name| {
+        let var = vars.iter().find(|(n, _)| *n == name);
+        var.map(|(_, value)| (*value).to_string())
+    }
+}
+
+/// Wraps a writer and strips color and style sequences (SGR) written to it when colors are
+/// disabled, other escape sequences are kept
+///
+/// Commands written to it, with [`queue!`](crate::queue), [`execute!`](crate::execute) or `write!`
+/// and also from code generic over [`Write`], follow its colors instead of stdout's, so wrap
+/// stderr and files in one to give them their own policy
+///
+/// ```no_run
+/// use neutuino::ansi::{COLOR_RED_FG, Color, ColorWriter, STYLE_RESET, SetForeground};
+/// use std::io::{self, Write};
+///
+/// let mut stderr = ColorWriter::for_stream(io::stderr());
+/// writeln!(stderr, "{COLOR_RED_FG}error{STYLE_RESET}: something went wrong")?;
+/// neutuino::queue!(stderr, SetForeground(Color::Yellow), "warning", SetForeground(Color::Default))?;
+/// # Ok::<(), io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct ColorWriter<W: Write> {
+    inner: W,
+    colors: bool,
+    /// The unfinished escape sequence at the end of the last write
+    pending: Vec<u8>,
+}
+
+impl<W: Write> ColorWriter<W> {
+    /// Wraps `inner`, stripping colors from everything written unless `colors` is true
+    pub const fn new(inner: W, colors: bool) -> Self {
+        Self {
+            inner,
+            colors,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Wraps a stream with colors if [`color_policy`] enables them for it
+    pub fn for_stream(inner: W) -> Self
+    where
+        W: IsTerminal,
+    {
+        let colors = colors_enabled_for(&inner);
+        Self::new(inner, colors)
+    }
+
+    /// Whether colors are passed through
+    #[must_use]
+    pub const fn colors(&self) -> bool {
+        self.colors
+    }
+
+    /// Gets a reference to the wrapped writer
+    pub const fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Unwraps the writer, an unfinished escape sequence is dropped
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    /// Writes through the bytes that can't be part of an SGR sequence, stripping the rest
+    fn strip(&mut self, buf: &[u8]) -> io::Result<()> {
+        let mut start = 0;
+        for (i, &byte) in buf.iter().enumerate() {
+            if self.pending.is_empty() {
+                if byte == b'\x1b' {
+                    self.inner.write_all(&buf[start..i])?;
+                    self.pending.push(byte);
+                }
+                continue;
+            }
+            if byte == b'\x1b' {
+                self.inner.write_all(&self.pending)?;
+                self.pending.clear();
+                self.pending.push(byte);
+                continue;
+            }
+            self.pending.push(byte);
+            let sgr = match self.pending.as_slice() {
+                [b'\x1b'] | [b'\x1b', b'['] => continue,
+                [b'\x1b', b'[', .., final_byte] => match final_byte {
+                    b'0'..=b'9' | b';' | b':' => continue,
+                    b'm' => true,
+                    _ => false,
+                },
+                _ => false,
+            };
+            if !sgr {
+                self.inner.write_all(&self.pending)?;
+            }
+            self.pending.clear();
+            start = i + 1;
+        }
+        if self.pending.is_empty() {
+            self.inner.write_all(&buf[start..])?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for ColorWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.colors {
+            return self.inner.write(buf);
+        }
+        self.strip(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+
+    // Commands are formatted while they are written, so they follow this writer's colors even
+    // when written through generic code
+    fn write_fmt(&mut self, args: fmt::Arguments<'_>) -> io::Result<()> {
+        with_colors(self.colors, || write_fmt(self, args))
+    }
+}
+
+#[test]
+fn test_color_policy() {
+    use crate::color::{Color, SetForeground};
+
+    // Generic like most renderers, so it can't see that it is writing to a ColorWriter
+    fn render(writer: &mut impl Write) -> io::Result<()> {
+        crate::queue!(*writer, SetForeground(Color::Red), "x")
+    }
+
+    assert_eq!(auto_env_from(env_from(&[])), None);
+    assert_eq!(auto_env_from(env_from(&[("NO_COLOR", "1")])), Some(false));
+    assert_eq!(auto_env_from(env_from(&[("NO_COLOR", "")])), None);
+    assert_eq!(auto_env_from(env_from(&[("TERM", "dumb")])), Some(false));
+    assert_eq!(
+        auto_env_from(env_from(&[("NO_COLOR", "1"), ("FORCE_COLOR", "1")])),
+        Some(true)
+    );
+    assert_eq!(auto_env_from(env_from(&[("FORCE_COLOR", "0")])), None);
+    assert_eq!(
+        auto_env_from(env_from(&[("CLICOLOR_FORCE", "1")])),
+        Some(true)
+    );
+
+    let mut writer = ColorWriter::new(Vec::new(), false);
+    writer.write_all(b"\x1b[1;38;5").unwrap();
+    writer
+        .write_all(b";208mhi\x1b[0m \x1b[2K\x1b7\x1b\x1b[m!")
+        .unwrap();
+    assert_eq!(writer.into_inner(), b"hi \x1b[2K\x1b7\x1b!");
+
+    // Commands follow the writer they are written to whatever stdout is, even through render
+    for colors in [false, true] {
+        let mut writer = ColorWriter::new(Vec::new(), colors);
+        with_colors(!colors, || {
+            crate::queue!(writer, SetForeground(Color::Red), "x").unwrap();
+            render(&mut writer).unwrap();
+            write!(writer, "{}x", SetForeground(Color::Red)).unwrap();
+        });
+        let expected: &[u8] = if colors { b"\x1b[31mx" } else { b"x" };
+        assert_eq!(writer.into_inner(), expected.repeat(3));
+    }
+}
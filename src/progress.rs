@@ -0,0 +1,92 @@
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::command::{Command, impl_display};
+
+/// Whether a progress indicator may be showing, so deinitializing only clears it if needed
+static PROGRESS_SHOWN: AtomicBool = AtomicBool::new(false);
+
+/// The state of the progress indicator on the terminal's tab or taskbar entry
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum ProgressState {
+    /// Hides the progress indicator
+    #[default]
+    Clear,
+    Normal,
+    Error,
+    /// Shows activity without a percentage, which is ignored
+    Indeterminate,
+    Paused,
+}
+
+/// Sets the progress indicator on the terminal's tab or taskbar entry to a percentage, supported by
+/// Windows Terminal, ConEmu, Ghostty and others
+///
+/// Percentages over 100 are sent as 100, set it with [`update_progress`] or
+/// [`set_progress`](crate::ansi::set_progress) to have [`tui_deinit`](crate::control::tui_deinit)
+/// and [`cli_deinit`](crate::control::cli_deinit) clear it
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SetProgress(pub ProgressState, pub u8);
+
+impl Command for SetProgress {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        let Self(state, percent) = *self;
+        match state {
+            ProgressState::Clear => f.write_str("\x1b]9;4;0\x1b\\"),
+            ProgressState::Indeterminate => f.write_str("\x1b]9;4;3\x1b\\"),
+            state => write!(f, "\x1b]9;4;{};{}\x1b\\", state as u8, percent.min(100)),
+        }
+    }
+}
+
+impl_display!(SetProgress);
+
+/// Writes [`SetProgress`] to stdout, remembering whether the indicator is showing so
+/// [`tui_deinit`](crate::control::tui_deinit) and [`cli_deinit`](crate::control::cli_deinit) can
+/// clear it
+///
+/// # Errors
+/// If stdout can't be written to
+pub fn update_progress(state: ProgressState, percent: u8) -> io::Result<()> {
+    track_progress(state);
+    let mut stdout = io::stdout().lock();
+    SetProgress(state, percent).write_to(&mut stdout)?;
+    stdout.flush()
+}
+
+/// Remembers whether the progress indicator is showing after `state` is sent
+pub(crate) fn track_progress(state: ProgressState) {
+    PROGRESS_SHOWN.store(state != ProgressState::Clear, Ordering::Relaxed);
+}
+
+/// Clears the progress indicator if it was set with [`update_progress`] or
+/// [`set_progress`](crate::ansi::set_progress)
+///
+/// Terminals without progress indicators may show the sequence as a notification instead, so it
+/// is only sent when needed
+pub(crate) fn clear_progress() {
+    if PROGRESS_SHOWN.swap(false, Ordering::Relaxed) {
+        print!("{}", SetProgress(ProgressState::Clear, 0));
+    }
+}
+
+#[test]
+fn test_progress() {
+    assert_eq!(
+        SetProgress(ProgressState::Normal, 42).to_string(),
+        "\x1b]9;4;1;42\x1b\\"
+    );
+    assert_eq!(
+        SetProgress(ProgressState::Error, 250).to_string(),
+        "\x1b]9;4;2;100\x1b\\"
+    );
+    assert_eq!(
+        SetProgress(ProgressState::Indeterminate, 7).to_string(),
+        "\x1b]9;4;3\x1b\\"
+    );
+    assert_eq!(
+        SetProgress(ProgressState::Clear, 0).to_string(),
+        "\x1b]9;4;0\x1b\\"
+    );
+}
@@ -0,0 +1,104 @@
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+#[cfg(unix)]
+use crate::unix_input::read_byte;
+
+#[cfg(windows)]
+use crate::windows_input::read_byte;
+
+/// Primary device attributes request, every terminal replies to it so sending it after a query
+/// tells apart a terminal that ignores the query from one that is slow to reply
+const DEVICE_ATTRIBUTES: &str = "\x1b[c";
+
+/// Sends a query to the terminal and returns everything it replied before its device attributes
+///
+/// Raw mode has to be enabled or the reply is echoed and held back until Enter is pressed,
+/// anything typed while waiting for the reply is discarded
+///
+/// # Errors
+/// If stdout can't be written to,
+/// stdin can't be read from or
+/// the terminal didn't reply before the timeout
+pub(crate) fn query(request: &str, timeout: Duration) -> io::Result<Vec<u8>> {
+    let mut stdout = io::stdout().lock();
+    write!(stdout, "{request}{DEVICE_ATTRIBUTES}")?;
+    stdout.flush()?;
+
+    let deadline = Instant::now() + timeout;
+    let mut reply = Vec::new();
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let byte = read_byte(remaining)?.ok_or(io::ErrorKind::TimedOut)?;
+        reply.push(byte);
+        if byte == b'c'
+            && let Some(start) = device_attributes_start(&reply)
+        {
+            reply.truncate(start);
+            return Ok(reply);
+        }
+    }
+}
+
+/// Asks the terminal for the state of a private mode with DECRQM
+///
+/// Returns 0 if the terminal doesn't recognise the mode or DECRQM, 1 if it is set, 2 if it is
+/// reset, 3 if it is permanently set and 4 if it is permanently reset
+///
+/// # Errors
+/// If the query fails
+pub(crate) fn query_private_mode(mode: u16, timeout: Duration) -> io::Result<u8> {
+    let reply = query(&format!("\x1b[?{mode}$p"), timeout)?;
+    Ok(mode_reply(&reply, mode).unwrap_or(0))
+}
+
+/// Gets the status from a DECRQM reply, `ESC [ ? mode ; status $ y`
+pub(crate) fn mode_reply(reply: &[u8], mode: u16) -> Option<u8> {
+    let introducer = format!("\x1b[?{mode};");
+    let start = reply
+        .windows(introducer.len())
+        .position(|window| window == introducer.as_bytes())?
+        + introducer.len();
+    match reply.get(start..start + 3)? {
+        [status @ b'0'..=b'4', b'$', b'y'] => Some(status - b'0'),
+        _ => None,
+    }
+}
+
+/// Finds the start of a device attributes reply, `ESC [ ? digits and ; c`, at the end of `reply`
+fn device_attributes_start(reply: &[u8]) -> Option<usize> {
+    let params = reply[..reply.len() - 1]
+        .iter()
+        .rev()
+        .take_while(|b| b.is_ascii_digit() || **b == b';')
+        .count();
+    let start = reply.len().checked_sub(params + 4)?;
+    (&reply[start..start + 3] == b"\x1b[?").then_some(start)
+}
+
+/// Gets the contents of a string reply (DCS, OSC) starting with `introducer`, up to the string
+/// terminator or BEL
+pub(crate) fn string_reply<'a>(reply: &'a [u8], introducer: &str) -> Option<&'a str> {
+    let introducer = introducer.as_bytes();
+    let start = reply
+        .windows(introducer.len())
+        .position(|window| window == introducer)?
+        + introducer.len();
+    let rest = &reply[start..];
+    let end = rest.iter().position(|b| matches!(b, b'\x1b' | b'\x07'))?;
+    std::str::from_utf8(&rest[..end]).ok()
+}
+
+#[test]
+fn test_reply_parsing() {
+    let reply = b"\x1bP1$r48:2::1:2:3m\x1b\\\x1b[?62;22c";
+    let start = device_attributes_start(reply).unwrap();
+    assert_eq!(start, 19);
+    assert_eq!(
+        string_reply(&reply[..start], "\x1bP1$r"),
+        Some("48:2::1:2:3m")
+    );
+    assert_eq!(device_attributes_start(b"abc"), None);
+    assert_eq!(mode_reply(b"\x1b[?2027;3$y", 2027), Some(3));
+    assert_eq!(mode_reply(b"\x1b[?2026;1$y", 2027), None);
+}
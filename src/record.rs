@@ -0,0 +1,408 @@
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::io::{self, BufRead, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::input::{
+    ButtonType, Decoding, Event, InputSource, Key, Modifiers, MouseButton, key_helper,
+};
+
+#[cfg(unix)]
+use crate::unix_input::{parse_event, poll_input_raw};
+
+#[cfg(windows)]
+use crate::windows_input::poll_input_raw;
+
+const HEADER: &str = "neutuino-recording 1";
+
+/// Records terminal input to a writer while passing it through, for reproducing bug reports
+///
+/// Each poll that does not time out is written as one line of the form
+/// `<milliseconds since start> <raw bytes in hex or -> <decoded event>`
+/// which can later be fed back with a [`Replayer`]
+///
+/// Raw bytes are only available on *nix, Windows delivers already decoded records
+///
+/// ```no_run
+/// use neutuino::input::{InputSource, Recorder};
+/// use std::{fs::File, time::Duration};
+///
+/// let mut input = Recorder::new(File::create("session.txt")?)?;
+/// let event = input.poll_event(Duration::from_millis(100));
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct Recorder<W: Write> {
+    writer: W,
+    start: Instant,
+}
+
+impl<W: Write> Recorder<W> {
+    /// Starts a recording, writing the header immediately
+    ///
+    /// # Errors
+    /// If writing the header fails
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        writeln!(writer, "{HEADER}")?;
+        Ok(Self {
+            writer,
+            start: Instant::now(),
+        })
+    }
+
+    /// Stops recording, giving back the writer
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write> InputSource for Recorder<W> {
+    /// Polls the terminal like [`poll_input`] and records the result
+    ///
+    /// [`poll_input`]: crate::input::poll_input
+    ///
+    /// # Errors
+    /// If the timeout has expired,
+    /// there was an error getting the data or
+    /// writing to the recording failed
+    fn poll_event(&mut self, timeout: Duration) -> io::Result<Event> {
+        let (result, bytes) = poll_input_raw(timeout);
+        if result
+            .as_ref()
+            .is_err_and(|e| e.kind() == io::ErrorKind::TimedOut)
+        {
+            return result;
+        }
+        let line = encode_record(self.start.elapsed(), &bytes, &result);
+        self.writer.write_all(line.as_bytes())?;
+        self.writer.flush()?;
+        result
+    }
+}
+
+struct Record {
+    time: Duration,
+    bytes: Vec<u8>,
+    result: Result<Event, String>,
+}
+
+/// Plays back a recording made by a [`Recorder`] as an input source
+///
+/// Events are returned as soon as they are polled so tests run deterministically and quickly,
+/// once the recording runs out polling fails with [`io::ErrorKind::UnexpectedEof`]
+pub struct Replayer {
+    records: VecDeque<Record>,
+    real_time: bool,
+    start: Option<Instant>,
+    #[cfg(unix)]
+    decode_bytes: bool,
+    #[cfg(unix)]
+    queued: VecDeque<Event>,
+}
+
+impl Replayer {
+    /// Reads a whole recording
+    ///
+    /// # Errors
+    /// If reading fails or
+    /// the recording is malformed
+    pub fn new(reader: impl BufRead) -> io::Result<Self> {
+        let mut lines = reader.lines();
+        if lines.next().transpose()?.as_deref() != Some(HEADER) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Not a neutuino recording",
+            ));
+        }
+        let mut records = VecDeque::new();
+        for (number, line) in lines.enumerate() {
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+            let record = decode_record(&line).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Malformed recording on line {}", number + 2),
+                )
+            })?;
+            records.push_back(record);
+        }
+        Ok(Self {
+            records,
+            real_time: false,
+            start: None,
+            #[cfg(unix)]
+            decode_bytes: false,
+            #[cfg(unix)]
+            queued: VecDeque::new(),
+        })
+    }
+
+    /// Waits between events for as long as they were apart while recording
+    pub fn set_real_time(&mut self, real_time: bool) {
+        self.real_time = real_time;
+    }
+
+    /// Decodes the recorded raw bytes again instead of returning the recorded events
+    ///
+    /// Useful for checking if a decoding bug has been fixed
+    #[cfg(unix)]
+    pub fn set_decode_bytes(&mut self, decode_bytes: bool) {
+        self.decode_bytes = decode_bytes;
+    }
+
+    /// The number of events that have not been played back yet
+    #[must_use]
+    pub fn remaining(&self) -> usize {
+        self.records.len()
+    }
+}
+
+impl InputSource for Replayer {
+    fn poll_event(&mut self, timeout: Duration) -> io::Result<Event> {
+        #[cfg(unix)]
+        if self.decode_bytes {
+            if let Some(event) = self.queued.pop_front() {
+                return Ok(event);
+            }
+            // Events without bytes were decoded from an earlier sequence and are queued again
+            // when decoding it
+            while self.records.front().is_some_and(|r| r.bytes.is_empty()) {
+                self.records.pop_front();
+            }
+        }
+
+        let Some(record) = self.records.front() else {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        };
+        if self.real_time {
+            let due = *self.start.get_or_insert_with(Instant::now) + record.time;
+            let wait = due.saturating_duration_since(Instant::now());
+            if wait > timeout {
+                thread::sleep(timeout);
+                return Err(io::ErrorKind::TimedOut.into());
+            }
+            thread::sleep(wait);
+        }
+        let record = self.records.pop_front().unwrap();
+
+        #[cfg(unix)]
+        if self.decode_bytes {
+            let mut bytes = record.bytes.into_iter().map(Ok);
+            let first = bytes.next().unwrap()?;
+            return parse_event(first, &mut bytes, &mut self.queued);
+        }
+
+        record.result.map_err(io::Error::other)
+    }
+}
+
+fn encode_record(time: Duration, bytes: &[u8], result: &io::Result<Event>) -> String {
+    let mut line = format!("{} ", time.as_millis());
+    if bytes.is_empty() {
+        line.push('-');
+    }
+    for byte in bytes {
+        write!(line, "{byte:02x}").unwrap();
+    }
+    line.push(' ');
+    match result {
+        Ok(event) => encode_event(&mut line, event),
+        Err(e) => write!(line, "error {}", e.to_string().replace('\n', " ")).unwrap(),
+    }
+    line.push('\n');
+    line
+}
+
+fn decode_record(line: &str) -> Option<Record> {
+    let mut parts = line.splitn(3, ' ');
+    let time = Duration::from_millis(parts.next()?.parse().ok()?);
+    let hex = parts.next()?;
+    let bytes = if hex == "-" {
+        Vec::new()
+    } else {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+            .collect::<Option<_>>()?
+    };
+    let rest = parts.next()?;
+    let result = match rest.strip_prefix("error ") {
+        Some(message) => Err(message.to_string()),
+        None => Ok(decode_event(rest)?),
+    };
+    Some(Record {
+        time,
+        bytes,
+        result,
+    })
+}
+
+/// Writes an event as space separated words,
+/// modifiers and button types use the same letters as `key_helper`
+fn encode_event(out: &mut String, event: &Event) {
+    let flags = |button_type: &ButtonType, mods: &Modifiers| {
+        let mut flags = String::new();
+        for (on, c) in [(mods.alt, 'A'), (mods.ctrl, 'C'), (mods.shift, 'S')] {
+            if on {
+                flags.push(c);
+            }
+        }
+        match button_type {
+            ButtonType::Press => {}
+            ButtonType::Held => flags.push('*'),
+            ButtonType::Release => flags.push('-'),
+        }
+        if flags.is_empty() {
+            flags.push('_');
+        }
+        flags
+    };
+    match event {
+        Event::Key(key, button_type, mods, decoding) => {
+            let mut flags = flags(button_type, mods);
+            if *decoding == Decoding::Ambiguous {
+                flags = flags.replace('_', "") + "?";
+            }
+            write!(out, "key {} {flags}", encode_key(*key)).unwrap();
+        }
+        Event::Mouse(mods, button, button_type, x, y) => {
+            let flags = flags(button_type, mods);
+            let button = MOUSE_BUTTONS.iter().find(|(b, _)| b == button).unwrap().1;
+            write!(out, "mouse {button} {flags} {x} {y}").unwrap();
+        }
+        Event::Text(text) => {
+            // code points like the key encoding, separated by colons like kitty's text
+            let code_points: Vec<String> = text
+                .chars()
+                .map(|c| format!("{:x}", u32::from(c)))
+                .collect();
+            write!(out, "text {}", code_points.join(":")).unwrap();
+        }
+        Event::FocusGained => out.push_str("focus-gained"),
+        Event::FocusLost => out.push_str("focus-lost"),
+    }
+}
+
+fn decode_event(string: &str) -> Option<Event> {
+    let mut words = string.split(' ');
+    let event = match words.next()? {
+        "key" => {
+            let key = decode_key(words.next()?)?;
+            key_helper(words.next()?, key)
+        }
+        "mouse" => {
+            let name = words.next()?;
+            let button = MOUSE_BUTTONS.iter().find(|(_, n)| *n == name)?.0;
+            let Event::Key(_, button_type, mods, _) = key_helper(words.next()?, Key::Escape) else {
+                return None;
+            };
+            let x = words.next()?.parse().ok()?;
+            let y = words.next()?.parse().ok()?;
+            Event::Mouse(mods, button, button_type, x, y)
+        }
+        "text" => Event::Text(
+            words
+                .next()?
+                .split(':')
+                .map(|c| char::from_u32(u32::from_str_radix(c, 16).ok()?))
+                .collect::<Option<_>>()?,
+        ),
+        "focus-gained" => Event::FocusGained,
+        "focus-lost" => Event::FocusLost,
+        _ => return None,
+    };
+    words.next().is_none().then_some(event)
+}
+
+const MOUSE_BUTTONS: [(MouseButton, &str); 9] = [
+    (MouseButton::Left, "left"),
+    (MouseButton::Right, "right"),
+    (MouseButton::Middle, "middle"),
+    (MouseButton::WheelUp, "wheel-up"),
+    (MouseButton::WheelDown, "wheel-down"),
+    (MouseButton::WheelLeft, "wheel-left"),
+    (MouseButton::WheelRight, "wheel-right"),
+    (MouseButton::Unknown, "unknown"),
+    (MouseButton::None, "none"),
+];
+
+const NAMED_KEYS: [(Key, &str); 14] = [
+    (Key::Backspace, "backspace"),
+    (Key::Up, "up"),
+    (Key::Down, "down"),
+    (Key::Left, "left"),
+    (Key::Right, "right"),
+    (Key::Home, "home"),
+    (Key::End, "end"),
+    (Key::PageUp, "page-up"),
+    (Key::PageDown, "page-down"),
+    (Key::Tab, "tab"),
+    (Key::Delete, "delete"),
+    (Key::Insert, "insert"),
+    (Key::Escape, "escape"),
+    (Key::Enter, "enter"),
+];
+
+fn encode_key(key: Key) -> String {
+    match key {
+        Key::F(n) => format!("f{n}"),
+        // characters are written as code points so spaces and newlines can't break the line
+        Key::Char(c) => format!("u+{:x}", u32::from(c)),
+        key => NAMED_KEYS
+            .iter()
+            .find(|(k, _)| *k == key)
+            .unwrap()
+            .1
+            .to_string(),
+    }
+}
+
+fn decode_key(string: &str) -> Option<Key> {
+    if let Some(hex) = string.strip_prefix("u+") {
+        return char::from_u32(u32::from_str_radix(hex, 16).ok()?).map(Key::Char);
+    }
+    if let Some(n) = string.strip_prefix('f') {
+        return n.parse().ok().map(Key::F);
+    }
+    NAMED_KEYS
+        .iter()
+        .find(|(_, name)| *name == string)
+        .map(|(key, _)| *key)
+}
+
+#[test]
+fn test_record_replay() {
+    let events = [
+        key_helper("", Key::Char(' ')),
+        key_helper("CS-", Key::F(5)),
+        key_helper("A*", Key::PageDown),
+        key_helper("?", Key::Enter),
+        Event::Mouse(
+            Modifiers::NONE,
+            MouseButton::WheelUp,
+            ButtonType::Press,
+            3,
+            9,
+        ),
+        Event::Text("é ü".to_string()),
+        Event::FocusLost,
+    ];
+    let mut recording = format!("{HEADER}\n");
+    for (i, event) in events.iter().enumerate() {
+        let time = Duration::from_millis(i as u64 * 40);
+        recording.push_str(&encode_record(time, b"\x1b[A", &Ok(event.clone())));
+    }
+    let error = io::Error::other("Could not parse event");
+    recording.push_str(&encode_record(Duration::ZERO, &[], &Err(error)));
+
+    let mut replayer = Replayer::new(recording.as_bytes()).unwrap();
+    for event in events {
+        assert_eq!(replayer.poll_event(Duration::ZERO).unwrap(), event);
+    }
+    let error = replayer.poll_event(Duration::ZERO).unwrap_err();
+    assert_eq!(error.to_string(), "Could not parse event");
+    let error = replayer.poll_event(Duration::ZERO).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+}
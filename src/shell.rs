@@ -0,0 +1,143 @@
+use std::fmt;
+use std::path::Path;
+
+use crate::command::{Command, impl_display};
+
+/// Marks the start of a prompt (OSC 133 A), letting the terminal jump between prompts
+///
+/// Print this before the prompt, [`PromptEnd`] after it, [`CommandStart`] once the command is
+/// entered and [`CommandFinished`] after its output
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct PromptStart;
+
+/// Marks the end of a prompt and the start of the user's input (OSC 133 B)
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct PromptEnd;
+
+/// Marks the end of the user's input and the start of the command's output (OSC 133 C)
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct CommandStart;
+
+/// Marks the end of the command's output (OSC 133 D), optionally with its exit status which
+/// terminals use to highlight failed commands
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct CommandFinished(pub Option<i32>);
+
+impl Command for PromptStart {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        f.write_str("\x1b]133;A\x1b\\")
+    }
+}
+
+impl Command for PromptEnd {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        f.write_str("\x1b]133;B\x1b\\")
+    }
+}
+
+impl Command for CommandStart {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        f.write_str("\x1b]133;C\x1b\\")
+    }
+}
+
+impl Command for CommandFinished {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        match self.0 {
+            Some(status) => write!(f, "\x1b]133;D;{status}\x1b\\"),
+            None => f.write_str("\x1b]133;D\x1b\\"),
+        }
+    }
+}
+
+/// Tells the terminal the current working directory (OSC 7), used to open new tabs and windows
+/// in the same directory
+///
+/// The path is sent as a `file://` URI with everything but unreserved characters and separators
+/// percent-encoded, without a host the terminal assumes the local machine
+///
+/// ```no_run
+/// use neutuino::ansi::SetWorkingDirectory;
+///
+/// let cwd = std::env::current_dir()?;
+/// print!("{}", SetWorkingDirectory::new(&cwd));
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SetWorkingDirectory<'a> {
+    pub path: &'a Path,
+    pub host: Option<&'a str>,
+}
+
+impl<'a> SetWorkingDirectory<'a> {
+    #[must_use]
+    pub const fn new(path: &'a Path) -> Self {
+        Self { path, host: None }
+    }
+
+    /// Sets the hostname of the machine the path is on, some terminals ignore the directory if it
+    /// doesn't match their own
+    #[must_use]
+    pub const fn host(self, host: &'a str) -> Self {
+        Self {
+            host: Some(host),
+            ..self
+        }
+    }
+}
+
+impl Command for SetWorkingDirectory<'_> {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        f.write_str("\x1b]7;file://")?;
+        if let Some(host) = self.host {
+            write_encoded(f, host.bytes().filter(|&b| b != b'/'))?;
+        }
+        let path = self.path.as_os_str().as_encoded_bytes();
+        // Windows paths like `C:\Users` become `/C:/Users`
+        if path.first() != Some(&b'/') {
+            f.write_char('/')?;
+        }
+        let separators = path
+            .iter()
+            .map(|&b| if b == b'\\' && cfg!(windows) { b'/' } else { b });
+        write_encoded(f, separators)?;
+        f.write_str("\x1b\\")
+    }
+}
+
+/// Writes bytes percent-encoding everything but unreserved characters, `/` and `:`
+fn write_encoded(f: &mut impl fmt::Write, bytes: impl Iterator<Item = u8>) -> fmt::Result {
+    for byte in bytes {
+        if byte.is_ascii_alphanumeric() || b"-._~/:".contains(&byte) {
+            f.write_char(char::from(byte))?;
+        } else {
+            write!(f, "%{byte:02X}")?;
+        }
+    }
+    Ok(())
+}
+
+impl_display!(
+    PromptStart,
+    PromptEnd,
+    CommandStart,
+    CommandFinished,
+    SetWorkingDirectory<'_>
+);
+
+#[test]
+fn test_shell_integration() {
+    assert_eq!(PromptStart.to_string(), "\x1b]133;A\x1b\\");
+    assert_eq!(CommandFinished(Some(-1)).to_string(), "\x1b]133;D;-1\x1b\\");
+    assert_eq!(CommandFinished(None).to_string(), "\x1b]133;D\x1b\\");
+    assert_eq!(
+        SetWorkingDirectory::new(Path::new("/home/me/my files/ß\x1b"))
+            .host("box")
+            .to_string(),
+        "\x1b]7;file://box/home/me/my%20files/%C3%9F%1B\x1b\\"
+    );
+    assert_eq!(
+        SetWorkingDirectory::new(Path::new("/tmp")).to_string(),
+        "\x1b]7;file:///tmp\x1b\\"
+    );
+}
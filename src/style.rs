@@ -0,0 +1,386 @@
+use std::fmt::{self, Write};
+use std::ops::{BitOr, BitOrAssign};
+
+use crate::color::{Color, ColorDepth, color_depth};
+use crate::command::{Attribute, Command, impl_display};
+use crate::policy::colors_enabled;
+use crate::underline::{UnderlineStyle, styled_underlines};
+
+/// A set of [`Attribute`]s
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Attributes(u8);
+
+impl Attributes {
+    /// Every attribute in the order of their bits
+    const ALL: [Attribute; 8] = [
+        Attribute::Bold,
+        Attribute::Dim,
+        Attribute::Italic,
+        Attribute::Underline,
+        Attribute::Blinking,
+        Attribute::Reverse,
+        Attribute::Hidden,
+        Attribute::Strikethrough,
+    ];
+
+    /// No attributes
+    pub const EMPTY: Self = Self(0);
+
+    const fn bit(attribute: Attribute) -> u8 {
+        1 << attribute as u8
+    }
+
+    #[must_use]
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    #[must_use]
+    pub const fn contains(self, attribute: Attribute) -> bool {
+        self.0 & Self::bit(attribute) != 0
+    }
+
+    /// Adds an attribute to the set
+    #[must_use]
+    pub const fn with(self, attribute: Attribute) -> Self {
+        Self(self.0 | Self::bit(attribute))
+    }
+
+    /// Removes an attribute from the set
+    #[must_use]
+    pub const fn without(self, attribute: Attribute) -> Self {
+        Self(self.0 & !Self::bit(attribute))
+    }
+
+    pub fn insert(&mut self, attribute: Attribute) {
+        *self = self.with(attribute);
+    }
+
+    pub fn remove(&mut self, attribute: Attribute) {
+        *self = self.without(attribute);
+    }
+
+    /// The attributes in `self` but not in `other`
+    const fn difference(self, other: Self) -> Self {
+        Self(self.0 & !other.0)
+    }
+
+    /// The attributes in both `self` and `other`
+    const fn intersection(self, other: Self) -> Self {
+        Self(self.0 & other.0)
+    }
+
+    /// Iterates over the attributes in the set
+    pub fn iter(self) -> impl Iterator<Item = Attribute> {
+        Self::ALL.into_iter().filter(move |a| self.contains(*a))
+    }
+}
+
+impl From<Attribute> for Attributes {
+    fn from(attribute: Attribute) -> Self {
+        Self::EMPTY.with(attribute)
+    }
+}
+
+impl FromIterator<Attribute> for Attributes {
+    fn from_iter<I: IntoIterator<Item = Attribute>>(iter: I) -> Self {
+        iter.into_iter().fold(Self::EMPTY, Self::with)
+    }
+}
+
+impl BitOr for Attributes {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOr<Attribute> for Attributes {
+    type Output = Self;
+
+    fn bitor(self, rhs: Attribute) -> Self {
+        self.with(rhs)
+    }
+}
+
+impl BitOrAssign<Attribute> for Attributes {
+    fn bitor_assign(&mut self, rhs: Attribute) {
+        self.insert(rhs);
+    }
+}
+
+/// The colors and attributes of text
+///
+/// Writing a style as a command resets everything first, use [`Style::transition`] to switch
+/// between styles with as short a sequence as possible
+///
+/// ```no_run
+/// use neutuino::ansi::{Attribute, Color, Style};
+///
+/// let normal = Style::new();
+/// let warning = Style::new().fg(Color::Yellow).attribute(Attribute::Bold);
+/// println!(
+///     "{}warning{}: careful",
+///     Style::transition(normal, warning),
+///     Style::transition(warning, normal),
+/// );
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Style {
+    pub fg: Color,
+    pub bg: Color,
+    pub underline_color: Color,
+    /// Only used while the attributes contain [`Attribute::Underline`]
+    pub underline_style: UnderlineStyle,
+    pub attributes: Attributes,
+}
+
+impl Style {
+    /// The terminal's default style
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            fg: Color::Default,
+            bg: Color::Default,
+            underline_color: Color::Default,
+            underline_style: UnderlineStyle::Single,
+            attributes: Attributes::EMPTY,
+        }
+    }
+
+    #[must_use]
+    pub const fn fg(self, fg: Color) -> Self {
+        Self { fg, ..self }
+    }
+
+    #[must_use]
+    pub const fn bg(self, bg: Color) -> Self {
+        Self { bg, ..self }
+    }
+
+    #[must_use]
+    pub const fn underline_color(self, underline_color: Color) -> Self {
+        Self {
+            underline_color,
+            ..self
+        }
+    }
+
+    /// Underlines with a style
+    #[must_use]
+    pub const fn underline(self, underline_style: UnderlineStyle) -> Self {
+        Self {
+            underline_style,
+            ..self.attribute(Attribute::Underline)
+        }
+    }
+
+    #[must_use]
+    pub const fn attribute(self, attribute: Attribute) -> Self {
+        Self {
+            attributes: self.attributes.with(attribute),
+            ..self
+        }
+    }
+
+    /// Switches the terminal from the style `from` to the style `to` with the shortest sequence,
+    /// either changing only what differs or resetting everything and setting `to`
+    ///
+    /// Writes nothing if the styles are the same
+    #[must_use]
+    pub const fn transition(from: Self, to: Self) -> StyleTransition {
+        StyleTransition { from, to }
+    }
+
+    /// Converts to what the terminal can display, downsampling colors and dropping underline
+    /// styles and colors if unsupported
+    fn for_terminal(self, depth: ColorDepth, styled_underlines: bool) -> Self {
+        let underlined = self.attributes.contains(Attribute::Underline);
+        Self {
+            fg: self.fg.downsample(depth),
+            bg: self.bg.downsample(depth),
+            underline_color: if styled_underlines {
+                self.underline_color.downsample(depth)
+            } else {
+                Color::Default
+            },
+            underline_style: if styled_underlines && underlined {
+                self.underline_style
+            } else {
+                UnderlineStyle::Single
+            },
+            attributes: self.attributes,
+        }
+    }
+
+    /// Writes the SGR parameter turning on an attribute
+    fn write_attribute(self, attribute: Attribute, f: &mut impl Write) -> fmt::Result {
+        if attribute == Attribute::Underline {
+            self.underline_style.write_sgr(f, true)
+        } else {
+            write!(f, "{}", attribute.set_code())
+        }
+    }
+
+    /// Writes the SGR parameters setting everything that differs from the default style
+    fn write_set(self, f: &mut impl Write, sep: &mut &'static str) -> fmt::Result {
+        for attribute in self.attributes.iter() {
+            f.write_str(sep)?;
+            self.write_attribute(attribute, f)?;
+            *sep = ";";
+        }
+        self.write_colors(Self::new(), f, sep)
+    }
+
+    /// Writes the SGR parameters changing the colors that differ from `from`
+    fn write_colors(self, from: Self, f: &mut impl Write, sep: &mut &'static str) -> fmt::Result {
+        if self.fg != from.fg {
+            f.write_str(sep)?;
+            self.fg.write_sgr(f, 30)?;
+            *sep = ";";
+        }
+        if self.bg != from.bg {
+            f.write_str(sep)?;
+            self.bg.write_sgr(f, 40)?;
+            *sep = ";";
+        }
+        if self.underline_color != from.underline_color {
+            f.write_str(sep)?;
+            self.underline_color.write_underline_sgr(f)?;
+            *sep = ";";
+        }
+        Ok(())
+    }
+
+    /// Writes the SGR parameters changing only what differs from `from`
+    fn write_diff(self, from: Self, f: &mut impl Write) -> fmt::Result {
+        let mut sep = "";
+        let removed = from.attributes.difference(self.attributes);
+        let mut added = self.attributes.difference(from.attributes);
+        // Bold and dim share a reset, so one that stays has to be set again
+        let bold_dim = Attributes::from(Attribute::Bold) | Attribute::Dim;
+        if !removed.intersection(bold_dim).is_empty() {
+            added = added | self.attributes.intersection(bold_dim);
+        }
+        if self.underline_style != from.underline_style {
+            added = added | self.attributes.intersection(Attribute::Underline.into());
+        }
+        let mut reset_bold_dim = false;
+        for attribute in removed.iter() {
+            if matches!(attribute, Attribute::Bold | Attribute::Dim) {
+                if reset_bold_dim {
+                    continue;
+                }
+                reset_bold_dim = true;
+            }
+            write!(f, "{sep}{}", attribute.reset_code())?;
+            sep = ";";
+        }
+        for attribute in added.iter() {
+            f.write_str(sep)?;
+            self.write_attribute(attribute, f)?;
+            sep = ";";
+        }
+        self.write_colors(from, f, &mut sep)
+    }
+}
+
+impl Command for Style {
+    fn write_ansi(&self, f: &mut impl Write) -> fmt::Result {
+        if !colors_enabled() {
+            return Ok(());
+        }
+        f.write_str("\x1b[0")?;
+        let style = self.for_terminal(color_depth(), styled_underlines());
+        style.write_set(f, &mut ";")?;
+        f.write_char('m')
+    }
+}
+
+/// Switches the terminal from one style to another, see [`Style::transition`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct StyleTransition {
+    pub from: Style,
+    pub to: Style,
+}
+
+/// Counts the length of formatted output without storing it
+struct Counter(usize);
+
+impl Write for Counter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0 += s.len();
+        Ok(())
+    }
+}
+
+impl Command for StyleTransition {
+    fn write_ansi(&self, f: &mut impl Write) -> fmt::Result {
+        let (depth, styled) = (color_depth(), styled_underlines());
+        let from = self.from.for_terminal(depth, styled);
+        let to = self.to.for_terminal(depth, styled);
+        if from == to || !colors_enabled() {
+            return Ok(());
+        }
+        let mut diff = Counter(0);
+        to.write_diff(from, &mut diff)?;
+        let mut reset = Counter(1);
+        to.write_set(&mut reset, &mut ";")?;
+
+        f.write_str("\x1b[")?;
+        if diff.0 <= reset.0 {
+            to.write_diff(from, f)?;
+        } else {
+            f.write_char('0')?;
+            to.write_set(f, &mut ";")?;
+        }
+        f.write_char('m')
+    }
+}
+
+impl_display!(Style, StyleTransition);
+
+#[test]
+fn test_style_transition() {
+    crate::policy::with_full_terminal(|| {
+        let plain = Style::new();
+        let bold = plain.attribute(Attribute::Bold);
+        let bold_dim = bold.attribute(Attribute::Dim).fg(Color::Red);
+        let transition = |from, to| Style::transition(from, to).to_string();
+
+        assert_eq!(transition(plain, plain), "");
+        assert_eq!(transition(plain, bold), "\x1b[1m");
+        assert_eq!(transition(bold, plain), "\x1b[0m");
+        let red = plain.fg(Color::Red);
+        assert_eq!(transition(bold.fg(Color::Red), red), "\x1b[22m");
+        let teal = Color::Rgb(0, 128, 128);
+        assert_eq!(
+            transition(bold_dim.bg(teal), bold.fg(Color::Red).bg(teal)),
+            "\x1b[22;1m"
+        );
+        assert_eq!(
+            transition(bold_dim, plain.attribute(Attribute::Italic)),
+            "\x1b[0;3m"
+        );
+        assert_eq!(
+            transition(
+                plain,
+                plain.bg(Color::Indexed(4)).underline_color(Color::Blue)
+            ),
+            "\x1b[48;5;4;58;5;4m"
+        );
+        assert_eq!(bold_dim.to_string(), "\x1b[0;1;2;31m");
+
+        let curly = plain
+            .underline(UnderlineStyle::Curly)
+            .underline_color(Color::Red);
+        assert_eq!(transition(plain, curly), "\x1b[4:3;58;5;1m");
+        assert_eq!(
+            transition(curly, curly.underline(UnderlineStyle::Double)),
+            "\x1b[4:2m"
+        );
+        let fallback = curly.for_terminal(ColorDepth::TrueColor, false);
+        assert_eq!(fallback, plain.attribute(Attribute::Underline));
+    });
+}
@@ -0,0 +1,142 @@
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use crate::command::{Command, impl_display};
+use crate::query::query_private_mode;
+
+static SYNCHRONIZED_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+/// Sets whether the terminal supports synchronized output (mode 2026)
+pub fn set_synchronized_output(supported: bool) {
+    SYNCHRONIZED_OUTPUT.store(supported, Ordering::Relaxed);
+}
+
+/// Whether the terminal supports synchronized output, off until set by
+/// [`detect_synchronized_output`] or [`set_synchronized_output`]
+#[must_use]
+pub fn synchronized_output() -> bool {
+    SYNCHRONIZED_OUTPUT.load(Ordering::Relaxed)
+}
+
+/// Asks the terminal whether it supports synchronized output with DECRQM and sets
+/// [`set_synchronized_output`]
+///
+/// This needs raw mode to be enabled, a terminal that doesn't reply doesn't support it
+///
+/// # Errors
+/// If the terminal can't be written to or read from
+pub fn detect_synchronized_output(timeout: Duration) -> io::Result<bool> {
+    let supported = match query_private_mode(2026, timeout) {
+        Ok(status) => matches!(status, 1..=3),
+        Err(e) if e.kind() == io::ErrorKind::TimedOut => false,
+        Err(e) => return Err(e),
+    };
+    set_synchronized_output(supported);
+    Ok(supported)
+}
+
+/// Makes the terminal hold off drawing until [`EndSynchronizedUpdate`], so a frame is shown all
+/// at once instead of tearing
+///
+/// Only sent if [`synchronized_output`] is supported, terminals end the update on their own after
+/// a timeout in case it is never ended
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct BeginSynchronizedUpdate;
+
+/// Draws everything sent since [`BeginSynchronizedUpdate`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct EndSynchronizedUpdate;
+
+impl Command for BeginSynchronizedUpdate {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        if !synchronized_output() {
+            return Ok(());
+        }
+        f.write_str("\x1b[?2026h")
+    }
+}
+
+impl Command for EndSynchronizedUpdate {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        if !synchronized_output() {
+            return Ok(());
+        }
+        f.write_str("\x1b[?2026l")
+    }
+}
+
+impl_display!(BeginSynchronizedUpdate, EndSynchronizedUpdate);
+
+/// Wraps the output of a frame in a synchronized update, which is ended and flushed when this
+/// is dropped
+///
+/// ```no_run
+/// use neutuino::ansi::{Erase, SynchronizedUpdate};
+/// use std::io::{self, Write};
+///
+/// let mut frame = SynchronizedUpdate::begin(io::stdout().lock())?;
+/// neutuino::queue!(frame, Erase::Screen)?;
+/// write!(frame, "Hello, World!")?;
+/// frame.end()?;
+/// # Ok::<(), io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct SynchronizedUpdate<W: Write> {
+    writer: W,
+    ended: bool,
+}
+
+impl<W: Write> SynchronizedUpdate<W> {
+    /// Begins a synchronized update on `writer`
+    ///
+    /// # Errors
+    /// If writing to `writer` fails
+    pub fn begin(mut writer: W) -> io::Result<Self> {
+        BeginSynchronizedUpdate.write_to(&mut writer)?;
+        Ok(Self {
+            writer,
+            ended: false,
+        })
+    }
+
+    /// Ends the synchronized update and flushes, reporting errors that dropping would ignore
+    ///
+    /// # Errors
+    /// If writing to or flushing the writer fails
+    pub fn end(mut self) -> io::Result<()> {
+        self.ended = true;
+        EndSynchronizedUpdate.write_to(&mut self.writer)?;
+        self.writer.flush()
+    }
+}
+
+impl<W: Write> Write for SynchronizedUpdate<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl<W: Write> Drop for SynchronizedUpdate<W> {
+    fn drop(&mut self) {
+        if !self.ended {
+            let _ = EndSynchronizedUpdate.write_to(&mut self.writer);
+            let _ = self.writer.flush();
+        }
+    }
+}
+
+#[test]
+fn test_synchronized_update() {
+    let mut buf = Vec::new();
+    crate::policy::with_full_terminal(|| {
+        let mut frame = SynchronizedUpdate::begin(&mut buf).unwrap();
+        frame.write_all(b"frame").unwrap();
+    });
+    assert_eq!(buf, b"\x1b[?2026hframe\x1b[?2026l");
+}
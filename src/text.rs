@@ -0,0 +1,339 @@
+use std::fmt;
+
+use crate::command::{Command, impl_display};
+use crate::grapheme::{grapheme_clusters, grapheme_width_in, graphemes};
+use crate::hyperlink::{EndHyperlink, StartHyperlink};
+use crate::style::Style;
+
+/// A string with a style, optionally a hyperlink
+///
+/// Printing a span sets its style and resets it afterwards
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Span {
+    pub content: String,
+    pub style: Style,
+    /// The URI the span links to
+    pub link: Option<String>,
+}
+
+impl Span {
+    /// A span with the default style
+    #[must_use]
+    pub fn new(content: impl Into<String>) -> Self {
+        Self::styled(content, Style::new())
+    }
+
+    #[must_use]
+    pub fn styled(content: impl Into<String>, style: Style) -> Self {
+        Self {
+            content: content.into(),
+            style,
+            link: None,
+        }
+    }
+
+    /// Makes the span a hyperlink to `uri`
+    #[must_use]
+    pub fn link(self, uri: impl Into<String>) -> Self {
+        Self {
+            link: Some(uri.into()),
+            ..self
+        }
+    }
+
+    /// The number of columns the span takes up in the terminal
+    #[must_use]
+    pub fn width(&self) -> usize {
+        self.width_in(grapheme_clusters())
+    }
+
+    /// Cuts the span down to at most `width` columns without splitting grapheme clusters, a wide
+    /// character that doesn't fit is dropped
+    pub fn truncate(&mut self, width: usize) {
+        self.truncate_in(width, grapheme_clusters());
+    }
+
+    // Both measure grapheme by grapheme so a span truncated to its own width is left alone
+
+    fn width_in(&self, clusters: bool) -> usize {
+        graphemes(&self.content)
+            .map(|grapheme| grapheme_width_in(grapheme, clusters))
+            .sum()
+    }
+
+    fn truncate_in(&mut self, width: usize, clusters: bool) {
+        let mut used = 0;
+        let mut end = 0;
+        for grapheme in graphemes(&self.content) {
+            used += grapheme_width_in(grapheme, clusters);
+            if used > width {
+                break;
+            }
+            end += grapheme.len();
+        }
+        self.content.truncate(end);
+    }
+}
+
+impl From<&str> for Span {
+    fn from(content: &str) -> Self {
+        Self::new(content)
+    }
+}
+
+impl From<String> for Span {
+    fn from(content: String) -> Self {
+        Self::new(content)
+    }
+}
+
+impl Command for Span {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        Style::transition(Style::new(), self.style).write_ansi(f)?;
+        if let Some(link) = &self.link {
+            StartHyperlink::new(link).write_ansi(f)?;
+        }
+        f.write_str(&self.content)?;
+        if self.link.is_some() {
+            EndHyperlink.write_ansi(f)?;
+        }
+        Style::transition(self.style, Style::new()).write_ansi(f)
+    }
+}
+
+/// A line of spans
+///
+/// Printing a line only changes the style where it differs between spans, it shouldn't contain
+/// newlines
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Line {
+    pub spans: Vec<Span>,
+}
+
+impl Line {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { spans: Vec::new() }
+    }
+
+    /// Adds a span to the end of the line
+    pub fn push(&mut self, span: impl Into<Span>) {
+        self.spans.push(span.into());
+    }
+
+    /// The number of columns the line takes up in the terminal
+    #[must_use]
+    pub fn width(&self) -> usize {
+        self.spans.iter().map(Span::width).sum()
+    }
+
+    /// Cuts the line down to at most `width` columns, a wide character that doesn't fit is dropped
+    pub fn truncate(&mut self, width: usize) {
+        let mut remaining = width;
+        let mut kept = 0;
+        for span in &mut self.spans {
+            kept += 1;
+            let width = span.width();
+            if width >= remaining {
+                span.truncate(remaining);
+                break;
+            }
+            remaining -= width;
+        }
+        self.spans.truncate(kept);
+        self.spans.retain(|span| !span.content.is_empty());
+    }
+
+    /// Pads the line with unstyled spaces to at least `width` columns
+    pub fn pad(&mut self, width: usize) {
+        let padding = width.saturating_sub(self.width());
+        if padding > 0 {
+            self.push(" ".repeat(padding));
+        }
+    }
+
+    /// Truncates or pads the line to exactly `width` columns
+    pub fn fit(&mut self, width: usize) {
+        self.truncate(width);
+        self.pad(width);
+    }
+}
+
+impl From<Span> for Line {
+    fn from(span: Span) -> Self {
+        Self { spans: vec![span] }
+    }
+}
+
+impl From<&str> for Line {
+    fn from(content: &str) -> Self {
+        Span::new(content).into()
+    }
+}
+
+impl From<Vec<Span>> for Line {
+    fn from(spans: Vec<Span>) -> Self {
+        Self { spans }
+    }
+}
+
+impl FromIterator<Span> for Line {
+    fn from_iter<I: IntoIterator<Item = Span>>(iter: I) -> Self {
+        Self {
+            spans: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl Command for Line {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        let mut style = Style::new();
+        let mut link = None;
+        for span in &self.spans {
+            Style::transition(style, span.style).write_ansi(f)?;
+            style = span.style;
+            if span.link.as_deref() != link {
+                link = span.link.as_deref();
+                match link {
+                    Some(uri) => StartHyperlink::new(uri).write_ansi(f)?,
+                    None => EndHyperlink.write_ansi(f)?,
+                }
+            }
+            f.write_str(&span.content)?;
+        }
+        if link.is_some() {
+            EndHyperlink.write_ansi(f)?;
+        }
+        Style::transition(style, Style::new()).write_ansi(f)
+    }
+}
+
+/// Lines of spans
+///
+/// Printing text separates the lines with `\n`, in raw mode print the lines one at a time
+/// instead as the cursor doesn't go back to the start of the line
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Text {
+    pub lines: Vec<Line>,
+}
+
+impl Text {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { lines: Vec::new() }
+    }
+
+    /// Adds a line to the end of the text
+    pub fn push(&mut self, line: impl Into<Line>) {
+        self.lines.push(line.into());
+    }
+
+    /// The number of columns the widest line takes up in the terminal
+    #[must_use]
+    pub fn width(&self) -> usize {
+        self.lines.iter().map(Line::width).max().unwrap_or(0)
+    }
+
+    /// The number of lines
+    #[must_use]
+    pub fn height(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// Cuts every line down to at most `width` columns
+    pub fn truncate(&mut self, width: usize) {
+        self.lines.iter_mut().for_each(|line| line.truncate(width));
+    }
+
+    /// Pads every line with unstyled spaces to at least `width` columns
+    pub fn pad(&mut self, width: usize) {
+        self.lines.iter_mut().for_each(|line| line.pad(width));
+    }
+
+    /// Truncates or pads every line to exactly `width` columns
+    pub fn fit(&mut self, width: usize) {
+        self.lines.iter_mut().for_each(|line| line.fit(width));
+    }
+}
+
+impl From<Line> for Text {
+    fn from(line: Line) -> Self {
+        Self { lines: vec![line] }
+    }
+}
+
+impl From<Vec<Line>> for Text {
+    fn from(lines: Vec<Line>) -> Self {
+        Self { lines }
+    }
+}
+
+impl From<&str> for Text {
+    /// Splits the string into lines
+    fn from(content: &str) -> Self {
+        content.lines().map(Line::from).collect()
+    }
+}
+
+impl FromIterator<Line> for Text {
+    fn from_iter<I: IntoIterator<Item = Line>>(iter: I) -> Self {
+        Self {
+            lines: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl Command for Text {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        for (i, line) in self.lines.iter().enumerate() {
+            if i > 0 {
+                f.write_char('\n')?;
+            }
+            line.write_ansi(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl_display!(Span, Line, Text);
+
+#[test]
+fn test_text() {
+    use crate::color::Color;
+    crate::policy::with_colors(true, || {
+        let red = Style::new().fg(Color::Red);
+        let mut line = Line::from(vec![
+            Span::styled("ab", red),
+            Span::styled("漢字", red),
+            Span::new("c"),
+        ]);
+        assert_eq!(line.width(), 7);
+        assert_eq!(line.to_string(), "\x1b[31mab漢字\x1b[0mc");
+
+        let mut truncated = line.clone();
+        truncated.truncate(5);
+        assert_eq!(truncated.to_string(), "\x1b[31mab漢\x1b[0m");
+        line.fit(3);
+        assert_eq!(line.to_string(), "\x1b[31mab\x1b[0m ");
+        assert_eq!(line.width(), 3);
+
+        let text: Text = [Line::from("x"), Line::from(Span::styled("yz", red))]
+            .into_iter()
+            .collect();
+        assert_eq!((text.width(), text.height()), (2, 2));
+        assert_eq!(text.to_string(), "x\n\x1b[31myz\x1b[0m");
+    });
+
+    // A ZWJ sequence is one wide cluster in mode 2027 and three wide emoji without it
+    let family = Span::new("👨\u{200D}👩\u{200D}👧!");
+    for (clusters, width) in [(true, 3), (false, 7)] {
+        assert_eq!(family.width_in(clusters), width);
+        let mut truncated = family.clone();
+        truncated.truncate_in(width, clusters);
+        assert_eq!(truncated, family);
+        truncated.truncate_in(width - 1, clusters);
+        assert_eq!(truncated.content, "👨\u{200D}👩\u{200D}👧");
+        truncated.truncate_in(1, clusters);
+        assert_eq!(truncated.content, "");
+    }
+}
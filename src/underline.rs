@@ -0,0 +1,122 @@
+use std::env;
+use std::fmt;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use crate::command::{Command, impl_display};
+use crate::policy::colors_enabled;
+
+/// The shape of an underline
+///
+/// Styles other than `Single` are an extension from kitty, terminals without it get a single
+/// underline instead, see [`styled_underlines`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum UnderlineStyle {
+    #[default]
+    Single,
+    Double,
+    /// A wavy line, commonly used for spell-check and diagnostics
+    Curly,
+    Dotted,
+    Dashed,
+}
+
+impl UnderlineStyle {
+    /// Writes the SGR parameter turning on this underline, falling back to a single underline if
+    /// `styled` is false
+    pub(crate) fn write_sgr(self, f: &mut impl fmt::Write, styled: bool) -> fmt::Result {
+        match self {
+            Self::Single => f.write_str("4"),
+            _ if !styled => f.write_str("4"),
+            style => write!(f, "4:{}", style as u8 + 1),
+        }
+    }
+}
+
+/// 0 until support has been detected or set, then 1 for unsupported and 2 for supported
+static STYLED_UNDERLINES: AtomicU8 = AtomicU8::new(0);
+
+/// Sets whether the terminal supports underline styles and colors
+pub fn set_styled_underlines(supported: bool) {
+    STYLED_UNDERLINES.store(if supported { 2 } else { 1 }, Ordering::Relaxed);
+}
+
+/// Whether the terminal supports underline styles and colors
+///
+/// Detected from the environment with [`detect_styled_underlines`] unless set with
+/// [`set_styled_underlines`], if unsupported underline styles are sent as a single underline and
+/// underline colors are not sent, as older terminals misread their parameters as other styles
+#[must_use]
+pub fn styled_underlines() -> bool {
+    match STYLED_UNDERLINES.load(Ordering::Relaxed) {
+        1 => false,
+        2 => true,
+        _ => {
+            let supported = detect_styled_underlines();
+            set_styled_underlines(supported);
+            supported
+        }
+    }
+}
+
+/// Detects whether the terminal supports underline styles and colors from the `TERM`,
+/// `TERM_PROGRAM` and `VTE_VERSION` environment variables
+#[must_use]
+pub fn detect_styled_underlines() -> bool {
+    detect_styled_underlines_from(|name| env::var(name).ok())
+}
+
+fn detect_styled_underlines_from(var: impl Fn(&str) -> Option<String>) -> bool {
+    const TERMS: [&str; 5] = ["kitty", "wezterm", "foot", "ghostty", "alacritty"];
+    const PROGRAMS: [&str; 4] = ["WezTerm", "ghostty", "iTerm.app", "vscode"];
+    // VTE 0.51.2 added underline styles and colors
+    const VTE_MIN_VERSION: u32 = 5102;
+
+    var("TERM").is_some_and(|term| TERMS.iter().any(|t| term.contains(t)))
+        || var("TERM_PROGRAM").is_some_and(|program| PROGRAMS.contains(&program.as_str()))
+        || var("VTE_VERSION")
+            .and_then(|version| version.parse::<u32>().ok())
+            .is_some_and(|version| version >= VTE_MIN_VERSION)
+}
+
+/// Makes characters sent to the screen underlined with a style
+///
+/// Turn the underline off with [`ResetAttribute(Attribute::Underline)`](crate::ansi::ResetAttribute)
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SetUnderlineStyle(pub UnderlineStyle);
+
+impl Command for SetUnderlineStyle {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        if !colors_enabled() {
+            return Ok(());
+        }
+        f.write_str("\x1b[")?;
+        self.0.write_sgr(f, styled_underlines())?;
+        f.write_str("m")
+    }
+}
+
+impl_display!(SetUnderlineStyle);
+
+#[test]
+fn test_underline_style() {
+    let sgr = |style: UnderlineStyle, styled| {
+        let mut s = String::new();
+        style.write_sgr(&mut s, styled).unwrap();
+        s
+    };
+    assert_eq!(sgr(UnderlineStyle::Curly, true), "4:3");
+    assert_eq!(sgr(UnderlineStyle::Dashed, true), "4:5");
+    assert_eq!(sgr(UnderlineStyle::Curly, false), "4");
+
+    let detect = |vars| detect_styled_underlines_from(crate::policy::env_from(vars));
+    assert!(detect(&[("TERM", "xterm-kitty")]));
+    assert!(detect(&[
+        ("TERM", "xterm-256color"),
+        ("VTE_VERSION", "7600")
+    ]));
+    assert!(!detect(&[
+        ("TERM", "xterm-256color"),
+        ("VTE_VERSION", "5000")
+    ]));
+    assert!(!detect(&[("TERM", "linux")]));
+}
@@ -2,8 +2,7 @@ use std::ffi::{c_int, c_short, c_uint, c_ulong, c_ushort};
 use std::io;
 use std::sync::LazyLock;
 
-const ENABLE_MOUSE: &str = "\x1b[?1000h\x1b[?1002h\x1b[?1015h\x1b[?1006h\x1b[?1003h";
-const DISABLE_MOUSE: &str = "\x1b[?1006l\x1b[?1015l\x1b[?1002l\x1b[?1000l\x1b[?1003l";
+use crate::ansi::{DisableMouseInput, EnableMouseInput};
 
 unsafe extern "C" {
     fn ioctl(fd: c_int, request: c_ulong, argp: *mut u8) -> c_int;
@@ -78,7 +77,7 @@ static TERMIOS: LazyLock<Result<Termios, i32>> = LazyLock::new(|| {
 ///
 /// Never currently
 pub fn enable_mouse_input() -> io::Result<()> {
-    print!("{ENABLE_MOUSE}");
+    print!("{EnableMouseInput}");
     Ok(())
 }
 
@@ -88,7 +87,7 @@ pub fn enable_mouse_input() -> io::Result<()> {
 ///
 /// Never currently
 pub fn disable_mouse_input() -> io::Result<()> {
-    print!("{DISABLE_MOUSE}");
+    print!("{DisableMouseInput}");
     Ok(())
 }
 
@@ -165,11 +164,24 @@ pub fn disable_ansi() -> io::Result<()> {
 /// if stdout isn't a TTY, or
 /// if it fails to retrieve the terminal size
 pub fn get_terminal_size() -> io::Result<(u16, u16)> {
+    let winsize = get_winsize()?;
+    Ok((winsize.col, winsize.row))
+}
+
+/// Gets the size of the terminal in pixels as reported by the kernel, in (width, height) format
+///
+/// Many terminals leave this at 0, 0
+pub(crate) fn get_reported_pixel_size() -> io::Result<(u16, u16)> {
+    let winsize = get_winsize()?;
+    Ok((winsize.xpixel, winsize.ypixel))
+}
+
+fn get_winsize() -> io::Result<Winsize> {
     let mut winsize = Winsize::default();
     let ioctl_result = unsafe { ioctl(STDOUT_FILENO, TIOCGWINSZ, (&raw mut winsize).cast::<u8>()) };
 
     if ioctl_result == 0 {
-        Ok((winsize.col, winsize.row))
+        Ok(winsize)
     } else {
         Err(io::Error::last_os_error())
     }
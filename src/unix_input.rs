@@ -1,8 +1,13 @@
+use std::collections::VecDeque;
 use std::ffi::{c_int, c_short, c_ulong, c_void};
 use std::io;
+use std::sync::{Mutex, PoisonError};
 use std::time::Duration;
 
-use crate::input::{ButtonType, Event, Key, Modifiers, MouseButton, key_helper, simple_key};
+use crate::input::{
+    ButtonType, Decoding, Event, Key, LegacyKeyPolicy, Modifiers, MouseButton, escape_timeout,
+    key_helper, legacy_key_policy,
+};
 use crate::unix::{POLLIN, STDIN_FILENO};
 // Some of this input code has been modified from [termion](https://github.com/redox-os/termion)
 
@@ -12,17 +17,50 @@ use crate::unix::{POLLIN, STDIN_FILENO};
 /// If the timeout has expired or
 /// there was an error getting the data
 pub fn poll_input(timeout: Duration) -> io::Result<Event> {
+    poll_input_raw(timeout).0
+}
+
+/// Events decoded from the same sequence as a previously returned event
+static QUEUED_EVENTS: Mutex<VecDeque<Event>> = Mutex::new(VecDeque::new());
+
+/// Same as [`poll_input`] but also returns the raw bytes that were read to decode the event
+///
+/// Events queued from an earlier sequence are returned first with no bytes
+pub(crate) fn poll_input_raw(timeout: Duration) -> (io::Result<Event>, Vec<u8>) {
+    let mut queued = QUEUED_EVENTS.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some(event) = queued.pop_front() {
+        return (Ok(event), Vec::new());
+    }
+
     let result = poll_timeout(timeout);
     let mut read_iter = ReadIterator::new();
 
     let timed_out: io::Error = io::ErrorKind::TimedOut.into();
 
-    match result {
+    let event = match result {
+        1.. => match read_iter.next() {
+            Some(Ok(item)) => parse_event(item, &mut read_iter, &mut queued),
+            Some(Err(e)) => Err(e),
+            None => Err(timed_out),
+        },
+        0 => Err(timed_out),
+        _ => Err(io::Error::last_os_error()),
+    };
+    (event, read_iter.bytes)
+}
+
+/// Reads a single byte from stdin without decoding it, used for terminal query replies
+pub(crate) fn read_byte(timeout: Duration) -> io::Result<Option<u8>> {
+    match poll_timeout(timeout) {
         1.. => {
-            let item = read_iter.next().ok_or(timed_out)??;
-            parse_event(item, &mut read_iter)
+            let mut buf = 0u8;
+            match unsafe { read(STDIN_FILENO, (&raw mut buf).cast::<c_void>(), 1) } {
+                1.. => Ok(Some(buf)),
+                0 => Ok(None),
+                _ => Err(io::Error::last_os_error()),
+            }
         }
-        0 => Err(timed_out),
+        0 => Ok(None),
         _ => Err(io::Error::last_os_error()),
     }
 }
@@ -56,13 +94,33 @@ struct PollFD {
     revents: c_short,
 }
 
+/// How long to wait for each byte after the first of a sequence, sequences split across reads
+/// (common over SSH) still arrive well within this
+const CONTINUATION_TIMEOUT: Duration = Duration::from_millis(50);
+
+/// How long to wait for the next byte given the bytes read so far, the escape timeout only
+/// decides whether a bare ESC is the Escape key
+fn byte_timeout(bytes: &[u8], escape_timeout: Duration) -> Duration {
+    if bytes == b"\x1b" {
+        escape_timeout
+    } else {
+        CONTINUATION_TIMEOUT
+    }
+}
+
+/// Reads stdin a byte at a time, waiting a short while for each byte so sequences split across
+/// reads are still decoded as one event
 struct ReadIterator {
     buf: u8,
+    bytes: Vec<u8>,
 }
 
 impl ReadIterator {
-    fn new() -> Self {
-        Self { buf: 0 }
+    const fn new() -> Self {
+        Self {
+            buf: 0,
+            bytes: Vec::new(),
+        }
     }
 }
 
@@ -70,7 +128,7 @@ impl Iterator for ReadIterator {
     type Item = io::Result<u8>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let bytes_poll = poll_timeout(Duration::ZERO);
+        let bytes_poll = poll_timeout(byte_timeout(&self.bytes, escape_timeout()));
         let bytes_read = match bytes_poll {
             1.. => Some(Ok(unsafe {
                 read(STDIN_FILENO, (&raw mut self.buf).cast::<c_void>(), 1)
@@ -79,37 +137,70 @@ impl Iterator for ReadIterator {
             _ => Some(Err(io::Error::last_os_error())),
         };
         match bytes_read? {
-            Ok(1..) => Some(Ok(self.buf)),
+            Ok(1..) => {
+                self.bytes.push(self.buf);
+                Some(Ok(self.buf))
+            }
             Ok(0) => None,
             _ => Some(Err(io::Error::last_os_error())),
         }
     }
 }
 
-pub(crate) fn parse_event<I>(item: u8, iter: &mut I) -> io::Result<Event>
+/// Decodes the event starting with `item`
+///
+/// Some sequences decode to more than one event, the extra events are pushed to `queued`
+pub(crate) fn parse_event<I>(
+    item: u8,
+    iter: &mut I,
+    queued: &mut VecDeque<Event>,
+) -> io::Result<Event>
 where
     I: Iterator<Item = io::Result<u8>>,
 {
     match item {
-        b'\x1b' => parse_ansi_sequence(iter),
-        b'\r' => Ok(key_helper("", Key::Char('\r'))),
-        b'\n' => Ok(key_helper("C", Key::Char('j'))),
-        b'\t' => Ok(key_helper("", Key::Char('\t'))),
-        b'\x7f' => Ok(key_helper("", Key::Backspace)),
-        b'\0' => Ok(key_helper("C", Key::Char(' '))),
-        c @ b'\x01'..=b'\x1a' => Ok(key_helper("C", Key::Char((c + 96) as char))),
-        c @ b'\x1c'..=b'\x1f' => Ok(key_helper("C", Key::Char((c + 24) as char))),
-        c => {
-            let character = parse_utf8_char(c, iter)?;
-            Ok(Event::Key(
-                Key::Char(character),
-                ButtonType::Press,
-                Modifiers::NONE.shift(character.is_uppercase()),
-            ))
-        }
+        b'\x1b' => parse_ansi_sequence(iter, queued),
+        c @ (b'\0'..=b'\x1f' | b'\x7f') => Ok(parse_control_char(c, false, legacy_key_policy())),
+        c => Ok(parse_char(parse_utf8_char(c, iter)?, false)),
     }
 }
 
+/// Decodes a C0 control character or DEL, following the [`LegacyKeyPolicy`] for the ones that
+/// are shared with named keys
+fn parse_control_char(c: u8, alt: bool, policy: LegacyKeyPolicy) -> Event {
+    let named = policy == LegacyKeyPolicy::PreferNamedKeys;
+    let (flags, key) = match c {
+        // Always Enter to match Windows, which can tell it apart from Ctrl+M
+        b'\r' => ("?", Key::Enter),
+        b'\t' if named => ("?", Key::Tab),
+        b'\x08' if named => ("?", Key::Backspace),
+        b'\x1b' if named => ("?", Key::Escape),
+        b'\0' if named => ("C?", Key::Char(' ')),
+        b'\t' | b'\x08' | b'\n' => ("C?", Key::Char((c + 96) as char)),
+        b'\x01'..=b'\x1a' => ("C", Key::Char((c + 96) as char)),
+        b'\x7f' => ("", Key::Backspace),
+        // ESC, NUL and the rest are Ctrl with the symbols just above the uppercase letters
+        _ => ("C?", Key::Char((c + 64) as char)),
+    };
+    let mut event = key_helper(flags, key);
+    if let Event::Key(_, _, mods, _) = &mut event {
+        mods.alt = alt;
+    }
+    event
+}
+
+/// Shift is only inferred from the case, Caps Lock sends uppercase letters without it, so those
+/// are ambiguous
+fn parse_char(c: char, alt: bool) -> Event {
+    let decoding = if c.is_uppercase() {
+        Decoding::Ambiguous
+    } else {
+        Decoding::Exact
+    };
+    let mods = Modifiers::NONE.shift(c.is_uppercase()).alt(alt);
+    Event::Key(Key::Char(c), ButtonType::Press, mods, decoding)
+}
+
 fn parse_utf8_char<I>(c: u8, iter: &mut I) -> io::Result<char>
 where
     I: Iterator<Item = io::Result<u8>>,
@@ -126,40 +217,28 @@ where
     Err(error())
 }
 
-fn parse_ansi_sequence<I>(iter: &mut I) -> io::Result<Event>
+fn parse_ansi_sequence<I>(iter: &mut I, queued: &mut VecDeque<Event>) -> io::Result<Event>
 where
     I: Iterator<Item = io::Result<u8>>,
 {
     let error = io::Error::other("Could not parse event");
+    // A lone ESC is only the Escape key if nothing follows it within the escape timeout
     match iter.next() {
-        None => Ok(key_helper("", Key::Escape)),
+        None => Ok(parse_control_char(b'\x1b', false, legacy_key_policy())),
         Some(Ok(b'O')) => match iter.next() {
             Some(Ok(val @ b'P'..=b's')) => Ok(key_helper("", Key::F(1 + val - b'P'))),
             _ => Err(error),
         },
-        Some(Ok(b'[')) => parse_csi_sequence(iter).ok_or(error),
+        Some(Ok(b'[')) => parse_csi_sequence(iter, queued).ok_or(error),
         Some(Ok(c)) => match c {
-            b'\r' => Ok(key_helper("A", Key::Char('\r'))),
-            b'\n' => Ok(key_helper("CA", Key::Char('j'))),
-            b'\t' => Ok(key_helper("A", Key::Char('\t'))),
-            b'\x7f' => Ok(key_helper("A", Key::Backspace)),
-            b'\0' => Ok(key_helper("CA", Key::Char(' '))),
-            c @ b'\x01'..=b'\x1a' => Ok(key_helper("CA", Key::Char((c + 96) as char))),
-            c @ b'\x1c'..=b'\x1f' => Ok(key_helper("CA", Key::Char((c + 24) as char))),
-            c => {
-                let character = parse_utf8_char(c, iter)?;
-                Ok(Event::Key(
-                    Key::Char(character),
-                    ButtonType::Press,
-                    Modifiers::NONE.shift(character.is_uppercase()).alt(true),
-                ))
-            }
+            c @ (b'\0'..=b'\x1f' | b'\x7f') => Ok(parse_control_char(c, true, legacy_key_policy())),
+            c => Ok(parse_char(parse_utf8_char(c, iter)?, true)),
         },
         _ => Err(error),
     }
 }
 
-fn parse_csi_sequence<I>(iter: &mut I) -> Option<Event>
+fn parse_csi_sequence<I>(iter: &mut I, queued: &mut VecDeque<Event>) -> Option<Event>
 where
     I: Iterator<Item = io::Result<u8>>,
 {
@@ -177,13 +256,13 @@ where
         Some(Ok(b'Z')) => Some(key_helper("", Key::Tab)),
         Some(Ok(b'<')) => parse_xterm_mouse(iter),
         Some(Ok(b'M')) => Some(parse_x10_mouse(iter)),
-        Some(Ok(c @ b'0'..=b'9')) => parse_numbered_escape(iter, c),
+        Some(Ok(c @ b'0'..=b'9')) => parse_numbered_escape(iter, c, queued),
         None => Some(key_helper("A", Key::Char('['))),
         _ => None,
     }
 }
 
-fn parse_numbered_escape<I>(iter: &mut I, c: u8) -> Option<Event>
+fn parse_numbered_escape<I>(iter: &mut I, c: u8, queued: &mut VecDeque<Event>) -> Option<Event>
 where
     I: Iterator<Item = io::Result<u8>>,
 {
@@ -222,89 +301,127 @@ where
 
             Some(event)
         }
-        // Special key code.
+        // Special key code, with kitty keyboard handling the modifiers can also have an event type
+        // ESC [ key ; modifiers : event ~
         b'~' => {
-            let str_buf = String::from_utf8(buf).unwrap();
-
-            // This CSI sequence can be a list of semicolon-separated
-            // numbers.
-            let nums: Vec<u8> = str_buf.split(';').map(|n| n.parse().unwrap()).collect();
-
-            if nums.is_empty() {
-                return None;
-            }
-
-            // TODO: handle multiple values for key modififiers (ex: values
-            // [3, 2] means Shift+Delete)
-            if nums.len() > 1 {
-                return None;
-            }
-
-            match nums[0] {
-                1 | 7 => Some(key_helper("", Key::Home)),
-                2 => Some(key_helper("", Key::Insert)),
-                3 => Some(key_helper("", Key::Delete)),
-                4 | 8 => Some(key_helper("", Key::End)),
-                5 => Some(key_helper("", Key::PageUp)),
-                6 => Some(key_helper("", Key::PageDown)),
-                v @ 11..=15 => Some(key_helper("", Key::F(v - 10))),
-                v @ 17..=21 => Some(key_helper("", Key::F(v - 11))),
-                v @ 23..=24 => Some(key_helper("", Key::F(v - 12))),
-                _ => None,
-            }
+            let str_buf = String::from_utf8(buf).ok()?;
+            let mut fields = str_buf.split(';');
+            let key = match fields.next()?.parse::<u8>().ok()? {
+                1 | 7 => Key::Home,
+                2 => Key::Insert,
+                3 => Key::Delete,
+                4 | 8 => Key::End,
+                5 => Key::PageUp,
+                6 => Key::PageDown,
+                v @ 11..=15 => Key::F(v - 10),
+                v @ 17..=21 => Key::F(v - 11),
+                v @ 23..=26 => Key::F(v - 12),
+                v @ 28..=29 => Key::F(v - 13),
+                v @ 31..=34 => Key::F(v - 14),
+                _ => return None,
+            };
+            let (mods, button_type) = parse_kitty_modifiers(fields.next().unwrap_or("1"))?;
+            Some(Event::Key(key, button_type, mods, Decoding::Exact))
         }
+        // Kitty comprehensive keyboard handling:
+        // ESC [ key : shifted key : base key ; modifiers : event ; text u
         b'u' => {
-            let str_buf = String::from_utf8(buf).unwrap();
-            let mut iter = str_buf.split(';');
-            let key_code: u32 = iter.next()?.parse().ok()?;
-            let mut iter = iter.next().unwrap_or("0:1").split(':');
-            let modifier: u32 = iter.next()?.parse().ok()?;
-            let key_type: u32 = iter.next().unwrap_or("1").parse().ok()?;
-            println!("{str_buf}\r");
-            println!("{modifier}\r");
-
-            let char = char::from_u32(key_code);
-            // let shift = modifier & 1 == 1;
-            // let alt = modifier & 2 == 2;
-            // let ctrl = modifier & 4 == 4;
-            let button_type = match key_type {
-                1 => ButtonType::Press,
-                2 => ButtonType::Held,
-                3 => ButtonType::Release,
-                _ => return None,
+            let str_buf = String::from_utf8(buf).ok()?;
+            let mut fields = str_buf.split(';');
+            let key = parse_kitty_key(fields.next()?.split(':').next()?.parse().ok()?)?;
+            let (mods, button_type) = parse_kitty_modifiers(fields.next().unwrap_or("1"))?;
+            let text = match fields.next() {
+                Some(text) => text
+                    .split(':')
+                    .map(|c| char::from_u32(c.parse().ok()?))
+                    .collect::<Option<String>>()?,
+                None => String::new(),
             };
-
-            Some(Event::Key(Key::Char(char?), button_type, Modifiers::NONE))
+            if !text.is_empty() && button_type != ButtonType::Release {
+                queued.push_back(Event::Text(text));
+            }
+            Some(Event::Key(key, button_type, mods, Decoding::Exact))
         }
-        b'A' | b'B' | b'C' | b'D' | b'F' | b'H' => {
-            let str_buf = String::from_utf8(buf).unwrap();
-
-            // This CSI sequence can be a list of semicolon-separated
-            // numbers.
-            let nums: Vec<u8> = str_buf.split(';').map(|n| n.parse().unwrap()).collect();
-
-            if !(nums.len() == 2 && nums[0] == 1) {
+        // Cursor keys and F1-F4 with modifiers
+        // ESC [ 1 ; modifiers : event letter
+        b'A'..=b'D' | b'F' | b'H' | b'P'..=b'S' => {
+            let str_buf = String::from_utf8(buf).ok()?;
+            let (first, modifiers) = str_buf.split_once(';')?;
+            if first != "1" {
                 return None;
             }
-            let mods = nums[1] - 1;
-            let shift = mods & 1 == 1;
-            let alt = mods & 2 == 2;
-            let ctrl = mods & 4 == 4;
-            match c {
-                b'D' => Some(simple_key(Key::Left, shift, alt, ctrl)),
-                b'C' => Some(simple_key(Key::Right, shift, alt, ctrl)),
-                b'A' => Some(simple_key(Key::Up, shift, alt, ctrl)),
-                b'B' => Some(simple_key(Key::Down, shift, alt, ctrl)),
-                b'H' => Some(simple_key(Key::Home, shift, alt, ctrl)),
-                b'F' => Some(simple_key(Key::End, shift, alt, ctrl)),
-                _ => None,
-            }
+            let (mods, button_type) = parse_kitty_modifiers(modifiers)?;
+            let key = match c {
+                b'D' => Key::Left,
+                b'C' => Key::Right,
+                b'A' => Key::Up,
+                b'B' => Key::Down,
+                b'H' => Key::Home,
+                b'F' => Key::End,
+                _ => Key::F(1 + c - b'P'),
+            };
+            Some(Event::Key(key, button_type, mods, Decoding::Exact))
         }
 
         _ => None,
     }
 }
 
+/// Parses `modifiers:event` where modifiers are one more than a bitfield of
+/// shift, alt, ctrl, super, hyper, meta, caps lock and num lock
+///
+/// The event type is only sent with kitty comprehensive keyboard handling, which leaves the field
+/// empty when there are no modifiers but text follows
+fn parse_kitty_modifiers(string: &str) -> Option<(Modifiers, ButtonType)> {
+    let string = if string.is_empty() { "1" } else { string };
+    let (mods, event) = string.split_once(':').unwrap_or((string, "1"));
+    let mods = mods.parse::<u16>().ok()?.checked_sub(1)?;
+    let button_type = match event {
+        "1" => ButtonType::Press,
+        "2" => ButtonType::Held,
+        "3" => ButtonType::Release,
+        _ => return None,
+    };
+    let mods = Modifiers::new(mods & 1 == 1, mods & 2 == 2, mods & 4 == 4);
+    Some((mods, button_type))
+}
+
+/// Maps a kitty key code, which is a unicode code point or a private use code for functional keys
+fn parse_kitty_key(code: u32) -> Option<Key> {
+    let key = match code {
+        27 => Key::Escape,
+        13 | 57414 => Key::Enter,
+        9 => Key::Tab,
+        127 => Key::Backspace,
+        // F13-F35
+        #[allow(clippy::cast_possible_truncation)]
+        57376..=57398 => Key::F((code - 57363) as u8),
+        // Keypad digits, the operators are ordered the same as their ascii counterparts below
+        57399..=57408 => Key::Char(char::from_digit(code - 57399, 10)?),
+        57409 => Key::Char('.'),
+        57410 => Key::Char('/'),
+        57411 => Key::Char('*'),
+        57412 => Key::Char('-'),
+        57413 => Key::Char('+'),
+        57415 => Key::Char('='),
+        57416 => Key::Char(','),
+        57417 => Key::Left,
+        57418 => Key::Right,
+        57419 => Key::Up,
+        57420 => Key::Down,
+        57421 => Key::PageUp,
+        57422 => Key::PageDown,
+        57423 => Key::Home,
+        57424 => Key::End,
+        57425 => Key::Insert,
+        57426 => Key::Delete,
+        // Lock, media and modifier keys aren't supported
+        57344..=63743 => return None,
+        _ => Key::Char(char::from_u32(code)?),
+    };
+    Some(key)
+}
+
 fn parse_x10_mouse<I>(iter: &mut I) -> Event
 where
     I: Iterator<Item = io::Result<u8>>,
@@ -407,7 +524,7 @@ where
 #[test]
 fn test_parse_utf8() {
     let string = "abcéŷ¤£€ù%323";
-    let ref mut bytes = string.bytes().map(|x| Ok(x));
+    let bytes = &mut string.bytes().map(Ok);
     let chars = string.chars();
     for c in chars {
         let b = bytes.next().unwrap().unwrap();
@@ -415,3 +532,81 @@ fn test_parse_utf8() {
         assert!(c == character);
     }
 }
+
+#[test]
+fn test_escape_timeout() {
+    let timeout = Duration::from_millis(300);
+    assert_eq!(byte_timeout(b"\x1b", timeout), timeout);
+    assert_eq!(byte_timeout(b"", timeout), CONTINUATION_TIMEOUT);
+    assert_eq!(byte_timeout(b"\x1b[", timeout), CONTINUATION_TIMEOUT);
+    assert_eq!(byte_timeout("é".as_bytes(), timeout), CONTINUATION_TIMEOUT);
+}
+
+#[test]
+fn test_legacy_key_policy() {
+    let parse = |bytes: &[u8]| {
+        let mut iter = bytes[1..].iter().copied().map(Ok);
+        parse_event(bytes[0], &mut iter, &mut VecDeque::new()).unwrap()
+    };
+    assert_eq!(parse(b"\t"), key_helper("?", Key::Tab));
+    assert_eq!(parse(b"\x1b\r"), key_helper("A?", Key::Enter));
+    assert_eq!(parse(b"\x1b"), key_helper("?", Key::Escape));
+    assert_eq!(parse(b"\x1c"), key_helper("C?", Key::Char('\\')));
+    assert_eq!(parse(b"\x01"), key_helper("C", Key::Char('a')));
+    assert_eq!(parse(b"a"), key_helper("", Key::Char('a')));
+    assert_eq!(parse(b"\x1bA"), key_helper("AS?", Key::Char('A')));
+
+    // The policy is passed in rather than set globally so this can't race with other tests
+    let ctrl = |c, alt| parse_control_char(c, alt, LegacyKeyPolicy::PreferCtrlChars);
+    assert_eq!(ctrl(b'\t', false), key_helper("C?", Key::Char('i')));
+    assert_eq!(ctrl(b'\x08', true), key_helper("AC?", Key::Char('h')));
+    assert_eq!(ctrl(b'\x1b', false), key_helper("C?", Key::Char('[')));
+
+    // Enter is the same whatever the policy, like on Windows
+    for policy in [
+        LegacyKeyPolicy::PreferNamedKeys,
+        LegacyKeyPolicy::PreferCtrlChars,
+    ] {
+        assert_eq!(
+            parse_control_char(b'\r', false, policy),
+            key_helper("?", Key::Enter)
+        );
+    }
+}
+
+#[test]
+fn test_kitty_keyboard() {
+    let parse = |bytes: &[u8], queued: &mut VecDeque<Event>| {
+        let mut iter = bytes[1..].iter().copied().map(Ok);
+        parse_event(bytes[0], &mut iter, queued).unwrap()
+    };
+    let mut queued = VecDeque::new();
+    assert_eq!(parse(b"\x1b[27u", &mut queued), key_helper("", Key::Escape));
+    assert_eq!(
+        parse(b"\x1b[1;5:3D", &mut queued),
+        key_helper("C-", Key::Left)
+    );
+    assert_eq!(
+        parse(b"\x1b[3;2~", &mut queued),
+        key_helper("S", Key::Delete)
+    );
+    assert!(queued.is_empty());
+
+    // shift+a reports the physical key and the text it typed separately
+    assert_eq!(
+        parse(b"\x1b[97:65;2;65u", &mut queued),
+        key_helper("S", Key::Char('a'))
+    );
+    assert_eq!(queued.pop_front(), Some(Event::Text("A".to_string())));
+    assert_eq!(
+        parse(b"\x1b[97;2:3u", &mut queued),
+        key_helper("S-", Key::Char('a'))
+    );
+    assert!(queued.is_empty());
+
+    // Unmodified keys with text have an empty modifiers field
+    for sequence in [&b"\x1b[97;;97u"[..], b"\x1b[97;1:1;97u"] {
+        assert_eq!(parse(sequence, &mut queued), key_helper("", Key::Char('a')));
+        assert_eq!(queued.pop_front(), Some(Event::Text("a".to_string())));
+    }
+}
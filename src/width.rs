@@ -0,0 +1,81 @@
+use std::cmp::Ordering as CmpOrdering;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::grapheme::{grapheme_clusters, grapheme_width, graphemes};
+use crate::width_tables::{AMBIGUOUS, WIDE, ZERO};
+
+static AMBIGUOUS_WIDE: AtomicBool = AtomicBool::new(false);
+
+/// Sets whether East Asian ambiguous characters (e.g. `±`, `→`, Greek and Cyrillic letters) are
+/// treated as wide
+///
+/// Terminals configured for CJK usually display them as wide, everything else as narrow which is
+/// the default
+pub fn set_ambiguous_wide(wide: bool) {
+    AMBIGUOUS_WIDE.store(wide, Ordering::Relaxed);
+}
+
+/// Whether East Asian ambiguous characters are treated as wide, see [`set_ambiguous_wide`]
+#[must_use]
+pub fn ambiguous_wide() -> bool {
+    AMBIGUOUS_WIDE.load(Ordering::Relaxed)
+}
+
+fn in_table(table: &[(u32, u32)], c: char) -> bool {
+    let c = u32::from(c);
+    table
+        .binary_search_by(|&(start, end)| {
+            if end < c {
+                CmpOrdering::Less
+            } else if start > c {
+                CmpOrdering::Greater
+            } else {
+                CmpOrdering::Equal
+            }
+        })
+        .is_ok()
+}
+
+/// The number of columns a character takes up in the terminal
+///
+/// Control characters and combining marks take up none and East Asian wide characters and emoji
+/// take up two, the tables are generated from Unicode 14 by `scripts/width_tables.py`
+#[must_use]
+pub fn char_width(c: char) -> usize {
+    if c.is_ascii() {
+        usize::from(!c.is_ascii_control())
+    } else if c.is_control() || in_table(ZERO, c) {
+        0
+    } else if in_table(WIDE, c) || (ambiguous_wide() && in_table(AMBIGUOUS, c)) {
+        2
+    } else {
+        1
+    }
+}
+
+/// The number of columns a string takes up in the terminal
+///
+/// This is the sum of [`char_width`] of each character, or of [`grapheme_width`] of each
+/// grapheme cluster if the terminal measures them as a whole, escape sequences are not skipped
+#[must_use]
+pub fn display_width(s: &str) -> usize {
+    if grapheme_clusters() {
+        graphemes(s).map(grapheme_width).sum()
+    } else {
+        s.chars().map(char_width).sum()
+    }
+}
+
+#[test]
+fn test_width() {
+    assert_eq!(display_width("Hello, World!"), 13);
+    assert_eq!(display_width("漢字"), 4);
+    assert_eq!(display_width("ｈｉ"), 4);
+    assert_eq!(display_width("e\u{301}"), 1);
+    assert_eq!(display_width("🦀"), 2);
+    assert_eq!(display_width("\u{1100}\u{1161}"), 2);
+    assert_eq!(char_width('\x1b'), 0);
+    assert_eq!(char_width('\u{200B}'), 0);
+    assert_eq!(char_width('±'), 1);
+    assert!(in_table(AMBIGUOUS, '±'));
+}
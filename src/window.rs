@@ -0,0 +1,235 @@
+use std::fmt;
+use std::io;
+use std::time::Duration;
+
+use crate::command::{Command, StripControl, impl_display};
+use crate::control::get_terminal_size;
+use crate::query::query;
+
+#[cfg(unix)]
+use crate::unix::get_reported_pixel_size;
+
+#[cfg(windows)]
+use crate::windows::get_reported_pixel_size;
+
+/// How long to wait for the terminal to report its size in pixels
+const PIXEL_QUERY_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Sets the name of the window's icon, usually shown in the taskbar, without changing the title
+///
+/// Control characters in the name are dropped as they could end the escape sequence early
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SetIconName<T>(pub T);
+
+impl<T: fmt::Display> Command for SetIconName<T> {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        f.write_str("\x1b]1;")?;
+        fmt::Write::write_fmt(&mut StripControl(f), format_args!("{}", self.0))?;
+        f.write_str("\x1b\\")
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for SetIconName<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_ansi(f)
+    }
+}
+
+/// Saves the window title and icon name on the terminal's title stack
+///
+/// Push before changing the title and [`PopWindowTitle`] before exiting to give the user back
+/// their title, not every terminal has a title stack
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct PushWindowTitle;
+
+/// Restores the window title and icon name saved by [`PushWindowTitle`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct PopWindowTitle;
+
+/// Minimizes the window
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct IconifyWindow;
+
+/// Restores the window after [`IconifyWindow`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct DeiconifyWindow;
+
+/// Maximizes the window
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MaximizeWindow;
+
+/// Restores the window to its size before [`MaximizeWindow`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct UnmaximizeWindow;
+
+impl Command for PushWindowTitle {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        f.write_str("\x1b[22;0t")
+    }
+}
+
+impl Command for PopWindowTitle {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        f.write_str("\x1b[23;0t")
+    }
+}
+
+impl Command for IconifyWindow {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        f.write_str("\x1b[2t")
+    }
+}
+
+impl Command for DeiconifyWindow {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        f.write_str("\x1b[1t")
+    }
+}
+
+impl Command for MaximizeWindow {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        f.write_str("\x1b[9;1t")
+    }
+}
+
+impl Command for UnmaximizeWindow {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        f.write_str("\x1b[9;0t")
+    }
+}
+
+impl_display!(
+    PushWindowTitle,
+    PopWindowTitle,
+    IconifyWindow,
+    DeiconifyWindow,
+    MaximizeWindow,
+    UnmaximizeWindow
+);
+
+/// Asks the terminal for the size of the text area in cells, `(columns, rows)`
+///
+/// Returns `None` if the terminal doesn't report it, this needs raw mode to be enabled
+///
+/// # Errors
+/// If the terminal can't be written to or read from or
+/// it didn't reply before the timeout
+pub fn query_text_area_size(timeout: Duration) -> io::Result<Option<(u16, u16)>> {
+    query_window(18, 8, timeout)
+}
+
+/// Asks the terminal for the size of the text area in pixels, `(width, height)`
+///
+/// Returns `None` if the terminal doesn't report it, this needs raw mode to be enabled
+///
+/// # Errors
+/// If the terminal can't be written to or read from or
+/// it didn't reply before the timeout
+pub fn query_text_area_pixels(timeout: Duration) -> io::Result<Option<(u16, u16)>> {
+    query_window(14, 4, timeout)
+}
+
+/// Asks the terminal for the size of a cell in pixels, `(width, height)`
+///
+/// Returns `None` if the terminal doesn't report it, this needs raw mode to be enabled
+///
+/// # Errors
+/// If the terminal can't be written to or read from or
+/// it didn't reply before the timeout
+pub fn query_cell_size(timeout: Duration) -> io::Result<Option<(u16, u16)>> {
+    query_window(16, 6, timeout)
+}
+
+fn query_window(request: u8, kind: u8, timeout: Duration) -> io::Result<Option<(u16, u16)>> {
+    let reply = query(&format!("\x1b[{request}t"), timeout)?;
+    Ok(window_reply(&reply, kind))
+}
+
+/// Gets the size from a window report, `ESC [ kind ; height ; width t`, as `(width, height)`
+fn window_reply(reply: &[u8], kind: u8) -> Option<(u16, u16)> {
+    let introducer = format!("\x1b[{kind};");
+    let start = reply
+        .windows(introducer.len())
+        .position(|window| window == introducer.as_bytes())?
+        + introducer.len();
+    let rest = &reply[start..];
+    let end = rest.iter().position(|&b| b == b't')?;
+    let (height, width) = std::str::from_utf8(&rest[..end]).ok()?.split_once(';')?;
+    Some((width.parse().ok()?, height.parse().ok()?))
+}
+
+/// Gets the size of the terminal's text area in pixels
+///
+/// Returns in (width, height) format, if the OS doesn't know the size the terminal is asked for
+/// it which needs raw mode to be enabled
+///
+/// # Errors
+///
+/// If the terminal size can't be retrieved or
+/// the terminal doesn't report its size in pixels
+pub fn get_terminal_pixel_size() -> io::Result<(u16, u16)> {
+    match get_reported_pixel_size()? {
+        (0, _) | (_, 0) => match query_text_area_pixels(PIXEL_QUERY_TIMEOUT) {
+            Ok(size) => size.ok_or_else(unsupported),
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => Err(unsupported()),
+            Err(e) => Err(e),
+        },
+        size => Ok(size),
+    }
+}
+
+/// Gets the size of a single cell in pixels
+///
+/// Returns in (width, height) format, derived from the terminal size in pixels and cells or
+/// asked for like [`get_terminal_pixel_size`]
+///
+/// # Errors
+///
+/// If the terminal size can't be retrieved or
+/// the terminal doesn't report its size in pixels
+pub fn get_cell_pixel_size() -> io::Result<(u16, u16)> {
+    if let Some(size) = cell_size(get_reported_pixel_size()?, get_terminal_size()?) {
+        return Ok(size);
+    }
+    // A terminal that doesn't reply to 16t may still reply to 14t
+    match query_cell_size(PIXEL_QUERY_TIMEOUT) {
+        Ok(Some(size)) => return Ok(size),
+        Ok(None) => {}
+        Err(e) if e.kind() == io::ErrorKind::TimedOut => {}
+        Err(e) => return Err(e),
+    }
+    cell_size(get_terminal_pixel_size()?, get_terminal_size()?).ok_or_else(unsupported)
+}
+
+/// Divides the size in pixels by the size in cells, `None` if either is unknown
+fn cell_size((width, height): (u16, u16), (columns, rows): (u16, u16)) -> Option<(u16, u16)> {
+    let size = (width.checked_div(columns)?, height.checked_div(rows)?);
+    (size.0 > 0 && size.1 > 0).then_some(size)
+}
+
+fn unsupported() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        "the terminal doesn't report its size in pixels",
+    )
+}
+
+#[test]
+fn test_window() {
+    assert_eq!(SetIconName("vim").to_string(), "\x1b]1;vim\x1b\\");
+    assert_eq!(
+        SetIconName("a\x1b\\b\x07c\u{9c}").to_string(),
+        "\x1b]1;a\\bc\x1b\\"
+    );
+    let mut buf = Vec::new();
+    crate::queue!(buf, crate::command::SetWindowTitle("x\x1b]0;y\n")).unwrap();
+    assert_eq!(buf, b"\x1b]0;x]0;y\x1b\\");
+    assert_eq!(PushWindowTitle.to_string(), "\x1b[22;0t");
+    assert_eq!(window_reply(b"\x1b[8;24;80t", 8), Some((80, 24)));
+    assert_eq!(window_reply(b"\x1b[6;20;10t", 6), Some((10, 20)));
+    assert_eq!(window_reply(b"\x1b[4;480;640t", 6), None);
+    assert_eq!(window_reply(b"\x1b[4;480t", 4), None);
+    assert_eq!(cell_size((640, 480), (80, 24)), Some((8, 20)));
+    assert_eq!(cell_size((0, 0), (80, 24)), None);
+    assert_eq!(cell_size((640, 480), (0, 0)), None);
+}
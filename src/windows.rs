@@ -171,3 +171,12 @@ pub fn get_terminal_size() -> io::Result<(u16, u16)> {
     }
     Err(io::Error::last_os_error())
 }
+
+/// Gets the size of the terminal in pixels as reported by the console, in (width, height) format
+///
+/// The console doesn't report it so this is always 0, 0, the size has to be queried with escape
+/// sequences instead
+#[allow(clippy::unnecessary_wraps)]
+pub(crate) fn get_reported_pixel_size() -> io::Result<(u16, u16)> {
+    Ok((0, 0))
+}
@@ -1,10 +1,12 @@
-use crate::input::{Event, key_helper, Key};
+use crate::input::{Event, Key, key_helper};
 use crate::windows::get_stdin_handle;
 
+use std::collections::VecDeque;
 use std::io;
 use std::mem;
 use std::os::windows::raw::HANDLE;
-use std::time::Duration;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
 
 #[repr(C)]
 #[derive(Copy, Clone)]
@@ -44,6 +46,8 @@ union CharUnion {
     ascii_char: u8,
 }
 
+const VK_PACKET: u16 = 0xE7;
+
 unsafe extern "system" {
     fn ReadConsoleInputW(
         console_input: HANDLE,
@@ -94,6 +98,17 @@ pub fn poll_input(timeout: Duration) -> io::Result<Event> {
                 // more this will have to do
                 return Err(io::ErrorKind::Other.into());
             }
+            // Input methods and pasted unicode come as packets with no key behind them
+            if key_event.virtual_key_code == VK_PACKET {
+                static HIGH_SURROGATE: Mutex<Option<u16>> = Mutex::new(None);
+                let mut high = HIGH_SURROGATE
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner);
+                let unit = unsafe { key_event.u_char.unicode_char };
+                // The first half of a character outside the BMP, the rest is in the next packet
+                let text = decode_packet(&mut high, unit).ok_or(io::ErrorKind::Other)?;
+                return Ok(Event::Text(text));
+            }
             Ok(parse_key_event(&key_event))
         }
         _ => {
@@ -103,6 +118,66 @@ pub fn poll_input(timeout: Duration) -> io::Result<Event> {
     }
 }
 
+/// Same as [`poll_input`], Windows delivers decoded records instead of bytes so none are returned
+pub(crate) fn poll_input_raw(timeout: Duration) -> (io::Result<Event>, Vec<u8>) {
+    (poll_input(timeout), Vec::new())
+}
+
+/// Reads a single byte of a terminal query reply
+///
+/// Replies come through as key events, their characters are returned UTF-8 encoded
+pub(crate) fn read_byte(timeout: Duration) -> io::Result<Option<u8>> {
+    static PENDING: Mutex<VecDeque<u8>> = Mutex::new(VecDeque::new());
+    let mut pending = PENDING.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some(byte) = pending.pop_front() {
+        return Ok(Some(byte));
+    }
+
+    let handle = get_stdin_handle()?;
+    let deadline = Instant::now() + timeout;
+    loop {
+        #[allow(clippy::cast_possible_truncation)]
+        let wait_time_millis = deadline
+            .saturating_duration_since(Instant::now())
+            .as_millis() as u32;
+        if unsafe { WaitForSingleObject(handle, wait_time_millis) } != 0 {
+            return Ok(None);
+        }
+        let mut record: InputRecord = unsafe { mem::zeroed() };
+        let mut read = 0;
+        if unsafe { ReadConsoleInputW(handle, &raw mut record, 1, &raw mut read) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if record.event_type != 0x1 {
+            continue;
+        }
+        let key_event: KeyEventRecord = unsafe { record.event.key };
+        let num = u32::from(unsafe { key_event.u_char.unicode_char });
+        if key_event.key_down == 0 || num == 0 {
+            continue;
+        }
+        let c = char::from_u32(num).unwrap_or(char::REPLACEMENT_CHARACTER);
+        let mut buf = [0; 4];
+        let bytes = c.encode_utf8(&mut buf).as_bytes();
+        pending.extend(&bytes[1..]);
+        return Ok(Some(bytes[0]));
+    }
+}
+
+/// Decodes the UTF-16 unit of a `VK_PACKET` record, characters outside the BMP come as two
+/// records so a high surrogate is kept in `high` until the low one arrives
+fn decode_packet(high: &mut Option<u16>, unit: u16) -> Option<String> {
+    if (0xD800..0xDC00).contains(&unit) {
+        *high = Some(unit);
+        return None;
+    }
+    let units = high.take().into_iter().chain([unit]);
+    let text = char::decode_utf16(units)
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect();
+    Some(text)
+}
+
 fn parse_key_event(event: &KeyEventRecord) -> Event {
     let ctrl = event.control_key_state & (0x0008 | 0x0004) != 0; // LEFT_CTRL_PRESSED | RIGHT_CTRL_PRESSED
     let shift = event.control_key_state & 0x0010 != 0; // SHIFT_PRESSED
@@ -116,7 +191,7 @@ fn parse_key_event(event: &KeyEventRecord) -> Event {
                 key_helper("", Key::Tab)
             }
         }
-        0x0D => key_helper("", Key::Char('\n')),
+        0x0D => key_helper("", Key::Enter),
         0x1B => key_helper("", Key::Escape),
         0x21 => key_helper("", Key::PageUp),
         0x22 => key_helper("", Key::PageDown),
@@ -142,3 +217,30 @@ fn parse_key_event(event: &KeyEventRecord) -> Event {
         }
     }
 }
+
+#[test]
+fn test_enter() {
+    let event = KeyEventRecord {
+        key_down: 1,
+        repeat_count: 1,
+        virtual_key_code: 0x0D,
+        virtual_scan_code: 0,
+        u_char: CharUnion { unicode_char: 13 },
+        control_key_state: 0,
+    };
+    // Unix can't tell Enter from Ctrl+M so it marks it as ambiguous, the key is the same
+    assert_eq!(parse_key_event(&event), key_helper("", Key::Enter));
+}
+
+#[test]
+fn test_decode_packet() {
+    let mut high = None;
+    assert_eq!(decode_packet(&mut high, 0x00E9).as_deref(), Some("é"));
+    assert_eq!(decode_packet(&mut high, 0xD83E), None);
+    assert_eq!(decode_packet(&mut high, 0xDD80).as_deref(), Some("🦀"));
+    assert_eq!(high, None);
+    assert_eq!(
+        decode_packet(&mut high, 0xDD80).as_deref(),
+        Some("\u{FFFD}")
+    );
+}